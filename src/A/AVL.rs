@@ -1,8 +1,19 @@
-use std::cmp::Ordering;
-use std::collections::{HashMap, HashSet, VecDeque};
-use std::iter::FromIterator;
-use std::mem;
-use std::{marker::PhantomData, ptr::NonNull};
+#[cfg(feature = "bytes")]
+use crate::bytes;
+use crate::merge::{self, SetOp};
+use crate::query;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::{FromIterator, FusedIterator};
+use core::mem;
+use core::ops::{Bound, Index, RangeBounds};
+use core::{marker::PhantomData, ptr::NonNull};
 
 /// An AVL balanced tree with owned nodes.
 pub struct AVL<K: Ord, V> {
@@ -12,6 +23,78 @@ pub struct AVL<K: Ord, V> {
 }
 
 type OpNode<K: Ord, V> = Option<NonNull<Node<K, V>>>;
+type KeyBounds<'a, K> = (Option<&'a K>, Option<&'a K>);
+
+/// The AVL tree rule that is violated, reported by `AVL::validate`
+/// the depth of the root node is 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AVLValidationError {
+    /// A key is not within the range given by it's ancestors
+    UnsortedNode { depth: usize },
+    /// The stored height of a node is not one more than
+    /// the greater height of it's children
+    HeightMismatch {
+        depth: usize,
+        stored: isize,
+        expected: isize,
+    },
+    /// The stored subtree size of a node is not one more than
+    /// the sizes of it's children
+    SizeMismatch {
+        depth: usize,
+        stored: usize,
+        expected: usize,
+    },
+    /// The heights of the two children differ by more than one
+    Unbalanced { depth: usize, factor: isize },
+    /// A node does not point back to it's parent,
+    /// or the root has a parent
+    ParentMismatch { depth: usize },
+    /// The number of nodes in the tree is not the stored length
+    LenMismatch { counted: usize, stored: usize },
+}
+
+impl fmt::Display for AVLValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AVLValidationError::UnsortedNode { depth } => {
+                write!(f, "key out of order in a node at depth {}", depth)
+            }
+            AVLValidationError::HeightMismatch {
+                depth,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "node at depth {} has height {} but expected {}",
+                depth, stored, expected
+            ),
+            AVLValidationError::SizeMismatch {
+                depth,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "node at depth {} has size {} but expected {}",
+                depth, stored, expected
+            ),
+            AVLValidationError::Unbalanced { depth, factor } => write!(
+                f,
+                "unbalanced node at depth {} with balance factor {}",
+                depth, factor
+            ),
+            AVLValidationError::ParentMismatch { depth } => {
+                write!(f, "wrong parent pointer at depth {}", depth)
+            }
+            AVLValidationError::LenMismatch { counted, stored } => {
+                write!(f, "counted {} nodes but the length is {}", counted, stored)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AVLValidationError {}
 
 /// Inner Node to store data
 struct Node<K: Ord, V> {
@@ -21,6 +104,7 @@ struct Node<K: Ord, V> {
     left_node: OpNode<K, V>,
     right_node: OpNode<K, V>,
     height: isize,
+    size: usize, // the number of nodes in the subtree
 }
 
 impl<K: Ord, V> Node<K, V> {
@@ -32,6 +116,7 @@ impl<K: Ord, V> Node<K, V> {
             left_node: None,
             right_node: None,
             height: 1,
+            size: 1,
         }
     }
 
@@ -187,12 +272,67 @@ impl<K: Ord, V> Node<K, V> {
         });
     }
 
+    /// get the number of nodes in the subtree of a node
+    #[inline]
+    fn get_size(node: OpNode<K, V>) -> usize {
+        node.as_ref().map_or(0, |n| unsafe { (*n.as_ptr()).size })
+    }
+
+    /// update the subtree size of a node
+    /// and the sizes of children must be updated
+    #[inline]
+    fn update_size(node: OpNode<K, V>) {
+        let new_size =
+            Node::get_size(Node::get_left(node)) + Node::get_size(Node::get_right(node)) + 1;
+        if let Some(n) = node {
+            unsafe { (*n.as_ptr()).size = new_size };
+        }
+    }
+
     /// give a node compare with some K
     #[inline]
     fn compare_key(node: OpNode<K, V>, k: &K) -> Option<Ordering> {
         node.as_ref().map(|n| unsafe { (*n.as_ptr()).key.cmp(k) })
     }
 
+    /// get the next node in ascending order
+    fn get_successor(node: OpNode<K, V>) -> OpNode<K, V> {
+        let mut cur_node = Node::get_right(node);
+        if cur_node.is_some() {
+            while Node::get_left(cur_node).is_some() {
+                cur_node = Node::get_left(cur_node);
+            }
+            return cur_node;
+        }
+        // going up until coming from a left child
+        cur_node = node;
+        let mut parent = Node::get_parent(cur_node);
+        while parent.is_some() && Node::get_right(parent) == cur_node {
+            cur_node = parent;
+            parent = Node::get_parent(cur_node);
+        }
+        parent
+    }
+
+    /// get the next node in descending order
+    fn get_predecessor(node: OpNode<K, V>) -> OpNode<K, V> {
+        let mut cur_node = Node::get_left(node);
+        if cur_node.is_some() {
+            while Node::get_right(cur_node).is_some() {
+                cur_node = Node::get_right(cur_node);
+            }
+            return cur_node;
+        }
+        // going up until coming from a right child
+        cur_node = node;
+        let mut parent = Node::get_parent(cur_node);
+        while parent.is_some() && Node::get_left(parent) == cur_node {
+            cur_node = parent;
+            parent = Node::get_parent(cur_node);
+        }
+        parent
+    }
+
     /// Wrap a NonNull Node into a Box
     #[inline]
     fn boxed_node(node: OpNode<K, V>) -> Option<Box<Node<K, V>>> {
@@ -201,79 +341,13 @@ impl<K: Ord, V> Node<K, V> {
 }
 
 impl<K: Ord, V> AVL<K, V> {
-    /// For a given node this method will update all the heights of it's children using dynamic programming
-    /// and then update all the height of it's upper nodes
-    fn _update_nodes_height_down_up(&mut self, node: OpNode<K, V>) {
-        let mut todo = vec![node];
-        let mut updated = HashMap::<OpNode<K, V>, isize>::new();
-        // updates all heights of lower nodes
-        'outer: loop {
-            let c = todo.pop();
-            match c {
-                None => {
-                    if todo.is_empty() {
-                        break 'outer;
-                    } else {
-                        continue 'outer;
-                    }
-                }
-                Some(cur_node) => {
-                    let cur_left = Node::get_left(cur_node);
-                    let cur_right = Node::get_right(cur_node);
-                    let adjs: Vec<OpNode<K, V>> = vec![cur_left, cur_right]
-                        .into_iter()
-                        .filter(|n| n.is_some())
-                        .collect();
-
-                    if cur_node.is_none() && adjs.is_empty() && todo.is_empty() {
-                        break 'outer;
-                    } else if cur_node.is_none() && !adjs.is_empty() && todo.is_empty() {
-                        break 'outer;
-                    } else if cur_node.is_none() && adjs.is_empty() && !todo.is_empty() {
-                        continue 'outer;
-                    } else if cur_node.is_none() && !adjs.is_empty() && !todo.is_empty() {
-                        adjs.into_iter().for_each(|n| {
-                            todo.push(n);
-                        });
-                        continue 'outer;
-                    } else if cur_node.is_some() && adjs.is_empty() && todo.is_empty() {
-                        Node::set_height(cur_node, 1);
-                        break 'outer;
-                    } else if cur_node.is_some() && adjs.is_empty() && !todo.is_empty() {
-                        Node::set_height(cur_node, 1);
-                        updated.insert(cur_node, 1);
-                        continue 'outer;
-                    } else {
-                        //cur_node.is_some() && !adjs.is_empty() && (todo.is_empty() || !todo.is_empty()) {
-                        let adjs_not_seen: Vec<OpNode<K, V>> = adjs
-                            .iter()
-                            .map(|n| *n)
-                            .filter(|n| !updated.contains_key(n))
-                            .collect();
-                        if adjs_not_seen.is_empty() {
-                            // all child nodes have been updated
-                            let new_height =
-                                (*adjs.iter().flat_map(|n| updated.get(n)).max().unwrap()) + 1;
-
-                            cur_node.as_ref().map(|cur| unsafe {
-                                (*cur.as_ptr()).height = new_height;
-                            });
-                            updated.insert(cur_node, new_height);
-                            continue 'outer;
-                        } else {
-                            todo.push(cur_node);
-                            adjs_not_seen.into_iter().for_each(|n| {
-                                todo.push(n);
-                            });
-                            continue 'outer;
-                        }
-                    }
-                }
-            }
+    /// Recomputing the heights from a changed node up to the root
+    /// the heights of the changed node's children must be right
+    fn _update_heights_up(&mut self, mut cur_node: OpNode<K, V>) {
+        while cur_node.is_some() {
+            Node::update_height(cur_node);
+            cur_node = Node::get_parent(cur_node);
         }
-        // the input node and it's children have been updated
-        // now update it's upper nodes if possible
-        self._update_all_upper_nodes(node);
     }
 
     /// Because every time we call add or remove only one node will changed
@@ -297,11 +371,19 @@ impl<K: Ord, V> AVL<K, V> {
                 break;
             }
             Node::set_height(cur_parent, new_p_height);
-            cur_node = Node::get_parent(cur_parent);
+            cur_node = cur_parent;
             continue;
         }
     }
 
+    /// Updating the subtree sizes from a changed node up to the root
+    fn _update_sizes_up(&mut self, mut cur_node: OpNode<K, V>) {
+        while cur_node.is_some() {
+            Node::update_size(cur_node);
+            cur_node = Node::get_parent(cur_node);
+        }
+    }
+
     /// Get balance factor
     fn _get_balance_factor(&self, node: OpNode<K, V>) -> isize {
         if node.is_none() {
@@ -345,6 +427,67 @@ impl<K: Ord, V> AVL<K, V> {
         }
     }
 
+    /// Checking all the AVL tree rules by the stored
+    /// heights and sizes of each node and it's children
+    fn _validate(&self) -> Result<(), AVLValidationError> {
+        if Node::get_parent(self.root_node).is_some() {
+            return Err(AVLValidationError::ParentMismatch { depth: 0 });
+        }
+        let mut counted: usize = 0;
+        // each node is visited with it's depth and the range of keys
+        // given by the ancestors
+        let mut todo: Vec<(OpNode<K, V>, usize, KeyBounds<K>)> =
+            vec![(self.root_node, 0, (None, None))];
+        while let Some((cur_node, depth, (low, high))) = todo.pop() {
+            let n = match cur_node {
+                None => continue,
+                Some(n) => unsafe { &*n.as_ptr() },
+            };
+            counted += 1;
+            if low.is_some_and(|l| n.key <= *l) || high.is_some_and(|h| n.key >= *h) {
+                return Err(AVLValidationError::UnsortedNode { depth });
+            }
+            let left = n.left_node;
+            let right = n.right_node;
+            let l_height = Node::get_height(left);
+            let r_height = Node::get_height(right);
+            let expected = l_height.max(r_height) + 1;
+            if n.height != expected {
+                return Err(AVLValidationError::HeightMismatch {
+                    depth,
+                    stored: n.height,
+                    expected,
+                });
+            }
+            let expected = Node::get_size(left) + Node::get_size(right) + 1;
+            if n.size != expected {
+                return Err(AVLValidationError::SizeMismatch {
+                    depth,
+                    stored: n.size,
+                    expected,
+                });
+            }
+            let factor = l_height - r_height;
+            if factor.abs() > 1 {
+                return Err(AVLValidationError::Unbalanced { depth, factor });
+            }
+            for child in [left, right].iter().filter(|c| c.is_some()) {
+                if Node::get_parent(*child) != cur_node {
+                    return Err(AVLValidationError::ParentMismatch { depth: depth + 1 });
+                }
+            }
+            todo.push((left, depth + 1, (low, Some(&n.key))));
+            todo.push((right, depth + 1, (Some(&n.key), high)));
+        }
+        if counted != self.len {
+            return Err(AVLValidationError::LenMismatch {
+                counted,
+                stored: self.len,
+            });
+        }
+        Ok(())
+    }
+
     /// Right rotate for node `y`
     ///        y                              x
     ///       / \                           /   \
@@ -358,16 +501,18 @@ impl<K: Ord, V> AVL<K, V> {
         let x = Node::get_left(y);
         let t3 = Node::get_right(x);
 
-        Node::set_parent(t3, y);
-        Node::set_parent(y, x);
+        Node::set_left(y, t3);
+        Node::set_right(x, y);
         Node::set_parent(x, y_parent);
 
         if y_parent.is_none() {
             self.root_node = x;
         }
-        // node x and node y needs to update the height
+        // node x and node y needs to update the height and size
         Node::update_height(y);
         Node::update_height(x);
+        Node::update_size(y);
+        Node::update_size(x);
         self._update_all_upper_nodes(x);
     }
 
@@ -391,56 +536,56 @@ impl<K: Ord, V> AVL<K, V> {
         if y_parent.is_none() {
             self.root_node = x;
         }
-        // node x and node y needs to update the height
+        // node x and node y needs to update the height and size
         Node::update_height(y);
         Node::update_height(x);
+        Node::update_size(y);
+        Node::update_size(x);
         self._update_all_upper_nodes(x);
     }
 
     /// Private method for adding a key-value pair
-    fn _add_loop(&mut self, k: K, v: V) {
+    /// returns the number of nodes visited or modified
+    /// including the rotations for rebalancing
+    fn _add_loop(&mut self, k: K, v: V) -> usize {
         if self.root_node.is_none() {
             let new_node = Box::new(Node::new(k, v));
             let new_raw = NonNull::new(Box::into_raw(new_node));
             self.len += 1;
             self.root_node = new_raw;
-            return;
+            return 1;
         }
+        let mut touched: usize = 0;
         let mut todo = vec![self.root_node];
         'outer: loop {
             let c = todo.pop();
             match c {
                 None => {
-                    break 'outer;
+                    break 'outer touched;
                 }
                 Some(cur_node) => {
+                    touched += 1;
                     let cur_left = Node::get_left(cur_node);
                     let cur_right = Node::get_right(cur_node);
                     let cmp = Node::compare_key(cur_node, &k);
 
                     match cmp {
                         None => {
-                            break 'outer;
+                            break 'outer touched;
                         }
                         Some(Ordering::Equal) => {
                             cur_node.as_ref().map(|cur| unsafe {
                                 (*cur.as_ptr()).value = v;
                             });
-                            break 'outer;
+                            break 'outer touched;
                         }
                         Some(Ordering::Greater) => {
                             if cur_left.is_some() {
                                 todo.push(cur_left);
                                 continue 'outer;
                             } else {
-                                self.len += 1;
-                                let new_node = Box::new(Node::new(k, v));
-                                let new_raw = NonNull::new(Box::into_raw(new_node));
-                                Node::set_left(cur_node, new_raw);
-                                // try to rebalance
-                                self._update_nodes_height_down_up(self.root_node);
-                                self._try_to_rebalancing(new_raw);
-                                break 'outer;
+                                let (_, rotations) = self._attach_node(cur_node, true, k, v);
+                                break 'outer touched + 1 + rotations;
                             }
                         }
                         Some(Ordering::Less) => {
@@ -448,13 +593,8 @@ impl<K: Ord, V> AVL<K, V> {
                                 todo.push(cur_right);
                                 continue 'outer;
                             } else {
-                                self.len += 1;
-                                let new_node = Box::new(Node::new(k, v));
-                                let new_raw = NonNull::new(Box::into_raw(new_node));
-                                Node::set_right(cur_node, new_raw);
-                                self._update_nodes_height_down_up(self.root_node);
-                                self._try_to_rebalancing(new_raw);
-                                break 'outer;
+                                let (_, rotations) = self._attach_node(cur_node, false, k, v);
+                                break 'outer touched + 1 + rotations;
                             }
                         }
                     }
@@ -463,6 +603,71 @@ impl<K: Ord, V> AVL<K, V> {
         }
     }
 
+    /// Hanging a new node as the left or right child of a node
+    /// which has no child on that side and rebalancing the tree,
+    /// returns the new node and the number of rotations
+    fn _attach_node(
+        &mut self,
+        parent: OpNode<K, V>,
+        as_left: bool,
+        k: K,
+        v: V,
+    ) -> (NonNull<Node<K, V>>, usize) {
+        self.len += 1;
+        let new_node = NonNull::from(Box::leak(Box::new(Node::new(k, v))));
+        if as_left {
+            Node::set_left(parent, Some(new_node));
+        } else {
+            Node::set_right(parent, Some(new_node));
+        }
+        self._update_sizes_up(parent);
+        // try to rebalance
+        self._update_all_upper_nodes(Some(new_node));
+        let rotations = self._try_to_rebalancing(Some(new_node));
+        (new_node, rotations)
+    }
+
+    /// Finding the node of a key in one descent,
+    /// if the key is not in the tree a node with
+    /// the value made by `f` is added and returned
+    fn _get_or_add_node<F: FnOnce() -> V>(&mut self, k: K, f: F) -> NonNull<Node<K, V>> {
+        let mut cur_node = match self.root_node {
+            Some(root) => root,
+            None => {
+                let new_node = NonNull::from(Box::leak(Box::new(Node::new(k, f()))));
+                self.len += 1;
+                self.root_node = Some(new_node);
+                return new_node;
+            }
+        };
+        loop {
+            let (child, as_left) = match Node::compare_key(Some(cur_node), &k) {
+                Some(Ordering::Greater) => (Node::get_left(Some(cur_node)), true),
+                Some(Ordering::Less) => (Node::get_right(Some(cur_node)), false),
+                _ => return cur_node,
+            };
+            match child {
+                Some(c) => cur_node = c,
+                None => return self._attach_node(Some(cur_node), as_left, k, f()).0,
+            }
+        }
+    }
+
+    /// Linking the sorted nodes into a balanced subtree by making
+    /// the middle one the root, returns the root of the subtree
+    fn _link_sorted(nodes: &[OpNode<K, V>]) -> OpNode<K, V> {
+        if nodes.is_empty() {
+            return None;
+        }
+        let mid = nodes.len() / 2;
+        let root = nodes[mid];
+        Node::set_left(root, Self::_link_sorted(&nodes[..mid]));
+        Node::set_right(root, Self::_link_sorted(&nodes[mid + 1..]));
+        Node::update_height(root);
+        Node::update_size(root);
+        root
+    }
+
     /// Pop out the minimun node
     fn _pop_min_loop(&mut self) -> OpNode<K, V> {
         let cur_min = self._find_min_child(self.root_node);
@@ -513,12 +718,101 @@ impl<K: Ord, V> AVL<K, V> {
         }
     }
 
+    /// Find the minimum node inside the lower bound
+    fn _lower_bound_node(&self, bound: Bound<&K>) -> OpNode<K, V> {
+        let mut cur_node = self.root_node;
+        let mut found: OpNode<K, V> = None;
+        while let Some(n) = cur_node {
+            let key = unsafe { &(*n.as_ptr()).key };
+            let inside = match bound {
+                Bound::Included(start) => key >= start,
+                Bound::Excluded(start) => key > start,
+                Bound::Unbounded => true,
+            };
+            if inside {
+                found = cur_node;
+                cur_node = Node::get_left(cur_node);
+            } else {
+                cur_node = Node::get_right(cur_node);
+            }
+        }
+        found
+    }
+
+    /// Find the maximum node inside the upper bound
+    fn _upper_bound_node(&self, bound: Bound<&K>) -> OpNode<K, V> {
+        let mut cur_node = self.root_node;
+        let mut found: OpNode<K, V> = None;
+        while let Some(n) = cur_node {
+            let key = unsafe { &(*n.as_ptr()).key };
+            let inside = match bound {
+                Bound::Included(end) => key <= end,
+                Bound::Excluded(end) => key < end,
+                Bound::Unbounded => true,
+            };
+            if inside {
+                found = cur_node;
+                cur_node = Node::get_right(cur_node);
+            } else {
+                cur_node = Node::get_left(cur_node);
+            }
+        }
+        found
+    }
+
     /// Given a ref key and return the mut ref of value
     fn _get_mut(&mut self, k: &K) -> Option<&mut V> {
         self._get_node(k)
             .map(|n| unsafe { &mut (*n.as_ptr()).value })
     }
 
+    /// Finding the node with the n-th smallest key
+    /// with the subtree sizes
+    fn _select_node(&self, mut n: usize) -> OpNode<K, V> {
+        let mut cur_node = self.root_node;
+        while cur_node.is_some() {
+            let left = Node::get_left(cur_node);
+            let left_size = Node::get_size(left);
+            match n.cmp(&left_size) {
+                Ordering::Less => {
+                    cur_node = left;
+                }
+                Ordering::Equal => {
+                    break;
+                }
+                Ordering::Greater => {
+                    n -= left_size + 1;
+                    cur_node = Node::get_right(cur_node);
+                }
+            }
+        }
+        cur_node
+    }
+
+    /// Counting the keys less than the given key
+    /// and telling if the key is in the tree
+    fn _count_less(&self, k: &K) -> (usize, bool) {
+        let mut cur_node = self.root_node;
+        let mut less: usize = 0;
+        loop {
+            match Node::compare_key(cur_node, k) {
+                None => {
+                    break (less, false);
+                }
+                Some(Ordering::Equal) => {
+                    break (less + Node::get_size(Node::get_left(cur_node)), true);
+                }
+                Some(Ordering::Greater) => {
+                    cur_node = Node::get_left(cur_node);
+                }
+                Some(Ordering::Less) => {
+                    less += Node::get_size(Node::get_left(cur_node)) + 1;
+                    cur_node = Node::get_right(cur_node);
+                }
+            }
+        }
+    }
+
     // When all heights have been updated call this methods to
     // find the first unbalanced node from bottom to top
     fn _get_unbalanced_node(&mut self, mut cur_node: OpNode<K, V>) -> OpNode<K, V> {
@@ -539,10 +833,18 @@ impl<K: Ord, V> AVL<K, V> {
     }
 
     /// Rebalancing
-    fn _try_to_rebalancing(&mut self, cur_node: OpNode<K, V>) {
-        let unbalanced = self._get_unbalanced_node(cur_node);
-        if unbalanced.is_some() {
-            self._rebalancing(unbalanced);
+    /// A removal could leave more than one unbalanced node on the way up
+    /// so keep going until the root is reached
+    /// Returns the number of rotations
+    fn _try_to_rebalancing(&mut self, mut cur_node: OpNode<K, V>) -> usize {
+        let mut rotations: usize = 0;
+        loop {
+            let unbalanced = self._get_unbalanced_node(cur_node);
+            if unbalanced.is_none() {
+                break rotations;
+            }
+            rotations += self._rebalancing(unbalanced);
+            cur_node = Node::get_parent(unbalanced);
         }
     }
 
@@ -572,6 +874,23 @@ impl<K: Ord, V> AVL<K, V> {
         }
     }
 
+    /// Detach the maximum node of a left subtree so it can take the place
+    /// of a removed node, the left child of the maximum node is handed to
+    /// it's parent
+    /// Returns the detached node and the lowest node that needs rebalancing
+    fn _detach_left_max(&mut self, cur_left: OpNode<K, V>) -> (OpNode<K, V>, OpNode<K, V>) {
+        let cur_left_max = self._find_max_child(cur_left);
+        if cur_left_max.eq(&cur_left) {
+            Node::unlink(cur_left_max, Node::get_parent(cur_left_max));
+            (cur_left_max, cur_left_max)
+        } else {
+            let max_parent = Node::get_parent(cur_left_max);
+            Node::set_right(max_parent, Node::get_left(cur_left_max));
+            Node::set_left(cur_left_max, None);
+            (cur_left_max, max_parent)
+        }
+    }
+
     /// remove node
     fn _remove_node(&mut self, k: &K) -> OpNode<K, V> {
         let target_node = self._get_node(k);
@@ -584,63 +903,66 @@ impl<K: Ord, V> AVL<K, V> {
                 let cur_right = Node::get_right(cur_node);
 
                 if cur_left.is_some() && cur_right.is_some() && cur_parent.is_some() {
-                    let cur_left_max = self._find_max_child(cur_left);
-                    Node::unlink(cur_left_max, Node::get_parent(cur_left_max));
+                    let (cur_left_max, rebalance_from) = self._detach_left_max(cur_left);
                     Node::set_parent(cur_left_max, cur_parent);
                     Node::set_right(cur_left_max, cur_right);
                     if !cur_left_max.eq(&cur_left) {
                         Node::set_left(cur_left_max, cur_left);
                     }
-                    self._update_nodes_height_down_up(cur_left_max);
-                    self._try_to_rebalancing(cur_left_max);
+                    self._update_heights_up(rebalance_from);
+                    self._update_sizes_up(rebalance_from);
+                    self._try_to_rebalancing(rebalance_from);
                     return cur_node;
                 } else if cur_left.is_some() && cur_right.is_some() && cur_parent.is_none() {
-                    let cur_left_max = self._find_max_child(cur_left);
-                    Node::unlink(cur_left_max, Node::get_parent(cur_left_max));
+                    let (cur_left_max, rebalance_from) = self._detach_left_max(cur_left);
                     self.root_node = cur_left_max;
                     Node::set_parent(cur_left_max, None);
                     Node::set_right(cur_left_max, cur_right);
                     if !cur_left_max.eq(&cur_left) {
                         Node::set_left(cur_left_max, cur_left);
                     }
-                    self._update_nodes_height_down_up(cur_left_max);
-                    self._try_to_rebalancing(cur_left_max);
+                    self._update_heights_up(rebalance_from);
+                    self._update_sizes_up(rebalance_from);
+                    self._try_to_rebalancing(rebalance_from);
                     return cur_node;
                 } else if cur_left.is_some() && cur_right.is_none() && cur_parent.is_some() {
-                    let cur_left_max = self._find_max_child(cur_left);
-                    Node::unlink(cur_left_max, Node::get_parent(cur_left_max));
+                    let (cur_left_max, rebalance_from) = self._detach_left_max(cur_left);
                     Node::set_parent(cur_left_max, cur_parent);
                     if !cur_left_max.eq(&cur_left) {
                         Node::set_left(cur_left_max, cur_left);
                     }
-                    self._update_nodes_height_down_up(cur_left_max);
-                    self._try_to_rebalancing(cur_left_max);
+                    self._update_heights_up(rebalance_from);
+                    self._update_sizes_up(rebalance_from);
+                    self._try_to_rebalancing(rebalance_from);
                     return cur_node;
                 } else if cur_left.is_some() && cur_right.is_none() && cur_parent.is_none() {
-                    let cur_left_max = self._find_max_child(cur_left);
-                    Node::unlink(cur_left_max, Node::get_parent(cur_left_max));
+                    let (cur_left_max, rebalance_from) = self._detach_left_max(cur_left);
                     self.root_node = cur_left_max;
                     Node::set_parent(cur_left_max, None);
                     if !cur_left_max.eq(&cur_left) {
                         Node::set_left(cur_left_max, cur_left);
                     }
-                    self._update_nodes_height_down_up(cur_left_max);
-                    self._try_to_rebalancing(cur_left_max);
+                    self._update_heights_up(rebalance_from);
+                    self._update_sizes_up(rebalance_from);
+                    self._try_to_rebalancing(rebalance_from);
                     return cur_node;
                 } else if cur_left.is_none() && cur_right.is_some() && cur_parent.is_some() {
                     Node::set_parent(cur_right, cur_parent);
-                    self._update_nodes_height_down_up(cur_right);
+                    self._update_heights_up(cur_right);
+                    self._update_sizes_up(cur_right);
                     self._try_to_rebalancing(cur_right);
                     return cur_node;
                 } else if cur_left.is_none() && cur_right.is_some() && cur_parent.is_none() {
                     Node::set_parent(cur_right, None);
                     self.root_node = cur_right;
-                    self._update_nodes_height_down_up(cur_right);
+                    self._update_heights_up(cur_right);
+                    self._update_sizes_up(cur_right);
                     self._try_to_rebalancing(cur_right);
                     return cur_node;
                 } else if cur_left.is_none() && cur_right.is_none() && cur_parent.is_some() {
                     Node::unlink(cur_node, cur_parent);
-                    self._update_nodes_height_down_up(cur_parent);
+                    self._update_heights_up(cur_parent);
+                    self._update_sizes_up(cur_parent);
                     self._try_to_rebalancing(cur_parent);
                     return cur_node;
                 } else {
@@ -654,29 +976,36 @@ impl<K: Ord, V> AVL<K, V> {
 
     // For the heights of nodes will be updated after rotate
     // the rebalanceing methods is also heights updated after calling
-    fn _rebalancing(&mut self, cur_node: OpNode<K, V>) {
+    // Returns the number of rotations
+    fn _rebalancing(&mut self, cur_node: OpNode<K, V>) -> usize {
         let cur_left = Node::get_left(cur_node);
         let cur_right = Node::get_right(cur_node);
         let cur_b_factor = self._get_balance_factor(cur_node);
         let cur_left_b_factor = self._get_balance_factor(cur_left);
         let cur_right_b_factor = self._get_balance_factor(cur_right);
+        let mut rotations: usize = 0;
         if cur_b_factor > 1 && cur_left_b_factor >= 0 {
             self._right_rotate(cur_node);
+            rotations += 1;
         }
 
         if cur_b_factor < -1 && cur_right_b_factor <= 0 {
             self._left_rotate(cur_node);
+            rotations += 1;
         }
 
         if cur_b_factor > 1 && cur_left_b_factor < 0 {
             self._left_rotate(cur_left);
             self._right_rotate(cur_node);
+            rotations += 2;
         }
 
         if cur_b_factor < -1 && cur_right_b_factor > 0 {
             self._right_rotate(cur_right);
             self._left_rotate(cur_node);
+            rotations += 2;
         }
+        rotations
     }
 }
 
@@ -698,6 +1027,43 @@ impl<K: Ord, V> Drop for AVL<K, V> {
     }
 }
 
+/// Hooks for breaking the AVL tree rules in tests
+#[cfg(test)]
+impl<K: Ord, V> AVL<K, V> {
+    pub(crate) fn _test_set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    pub(crate) fn _test_set_root_height(&mut self, h: isize) {
+        Node::set_height(self.root_node, h);
+    }
+
+    /// Swapping the key of root with the key of it's left child
+    pub(crate) fn _test_swap_root_left_keys(&mut self) {
+        let left = Node::get_left(self.root_node);
+        if let (Some(r), Some(l)) = (self.root_node, left) {
+            unsafe { mem::swap(&mut (*r.as_ptr()).key, &mut (*l.as_ptr()).key) }
+        }
+    }
+
+    /// Unlinking the left child of root from it's parent
+    pub(crate) fn _test_break_left_parent(&mut self) {
+        if let Some(l) = Node::get_left(self.root_node) {
+            unsafe { (*l.as_ptr()).parent_node = None }
+        }
+    }
+
+    /// Cutting off the right subtree of root without rebalancing,
+    /// the removed nodes are leaked
+    pub(crate) fn _test_cut_right_subtree(&mut self) {
+        let right = Node::get_right(self.root_node);
+        self.len -= Node::get_size(right);
+        Node::set_right(self.root_node, None);
+        Node::update_height(self.root_node);
+        Node::update_size(self.root_node);
+    }
+}
+
 pub struct IntoIter<K: Ord, V>(AVL<K, V>);
 
 impl<K: Ord, V> Iterator for IntoIter<K, V> {
@@ -706,6 +1072,10 @@ impl<K: Ord, V> Iterator for IntoIter<K, V> {
     fn next(&mut self) -> Option<Self::Item> {
         self.0._pop_min().map(|n| Node::into_element(n))
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
 }
 
 impl<K: Ord, V> DoubleEndedIterator for IntoIter<K, V> {
@@ -714,6 +1084,10 @@ impl<K: Ord, V> DoubleEndedIterator for IntoIter<K, V> {
     }
 }
 
+impl<K: Ord, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: Ord, V> FusedIterator for IntoIter<K, V> {}
+
 impl<K: Ord, V> Drop for IntoIter<K, V> {
     fn drop(&mut self) {
         struct DropGuard<'a, K: Ord, V>(&'a mut IntoIter<K, V>);
@@ -732,106 +1106,97 @@ impl<K: Ord, V> Drop for IntoIter<K, V> {
     }
 }
 
+/// A draining iterator which leaves the tree empty when dropped
+pub struct Drain<'a, K: Ord, V>(&'a mut AVL<K, V>);
+
+impl<'a, K: Ord, V> Iterator for Drain<'a, K, V> {
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_min()
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for Drain<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_max()
+    }
+}
+
+impl<'a, K: Ord, V> Drop for Drain<'a, K, V> {
+    fn drop(&mut self) {
+        struct DropGuard<'b, 'a, K: Ord, V>(&'b mut Drain<'a, K, V>);
+
+        impl<'b, 'a, K: Ord, V> Drop for DropGuard<'b, 'a, K, V> {
+            fn drop(&mut self) {
+                while self.0.next().is_some() {}
+            }
+        }
+
+        while let Some(d) = self.next() {
+            let guard = DropGuard(self);
+            drop(d);
+            mem::forget(guard);
+        }
+    }
+}
+
 pub struct Iter<'a, K: Ord, V> {
-    next_nodes: Vec<OpNode<K, V>>,
-    seen: HashSet<NonNull<Node<K, V>>>,
-    next_back_nodes: Vec<OpNode<K, V>>,
-    seen_back: HashSet<NonNull<Node<K, V>>>,
+    // the nodes whose left subtrees are already pushed
+    next_nodes: Vec<NonNull<Node<K, V>>>,
+    // the nodes whose right subtrees are already pushed
+    next_back_nodes: Vec<NonNull<Node<K, V>>>,
+    // the number of pairs not yielded from either end
+    remaining: usize,
     _marker: PhantomData<&'a Node<K, V>>,
 }
 
 impl<'a, K: Ord, V> Iter<'a, K, V> {
+    fn new(root: OpNode<K, V>, len: usize) -> Self {
+        let mut iter = Iter {
+            next_nodes: Vec::new(),
+            next_back_nodes: Vec::new(),
+            remaining: len,
+            _marker: PhantomData,
+        };
+        iter.push_left_edge(root);
+        iter.push_right_edge(root);
+        iter
+    }
+
+    /// Pushing the node and all the left nodes below it
+    fn push_left_edge(&mut self, mut node: OpNode<K, V>) {
+        while let Some(n) = node {
+            self.next_nodes.push(n);
+            node = Node::get_left(node);
+        }
+    }
+
+    /// Pushing the node and all the right nodes below it
+    fn push_right_edge(&mut self, mut node: OpNode<K, V>) {
+        while let Some(n) = node {
+            self.next_back_nodes.push(n);
+            node = Node::get_right(node);
+        }
+    }
+
     fn next_ascending(&mut self) -> OpNode<K, V> {
-        loop {
-            let n = self.next_nodes.pop();
-            match n {
-                None => {
-                    if self.next_nodes.len() == 0 {
-                        break None;
-                    } else {
-                        continue;
-                    }
-                }
-                Some(node) => unsafe {
-                    let left = node
-                        .as_ref()
-                        .and_then(|n| (*n.as_ptr()).left_node)
-                        .filter(|n| !self.seen.contains(n));
-                    let right = node
-                        .as_ref()
-                        .and_then(|n| (*n.as_ptr()).right_node)
-                        .filter(|n| !self.seen.contains(n));
-
-                    if left.is_some() && right.is_some() {
-                        self.next_nodes.push(node);
-                        self.next_nodes.push(left);
-                        continue;
-                    } else if left.is_some() && right.is_none() {
-                        self.next_nodes.push(node);
-                        self.next_nodes.push(left);
-                        continue;
-                    } else if left.is_none() && right.is_some() {
-                        self.next_nodes.push(right);
-                        node.map(|n| {
-                            self.seen.insert(n);
-                        });
-                        break node;
-                    } else {
-                        node.map(|n| {
-                            self.seen.insert(n);
-                        });
-                        break node;
-                    }
-                },
-            }
+        if self.remaining == 0 {
+            return None;
         }
+        self.remaining -= 1;
+        let node = self.next_nodes.pop();
+        self.push_left_edge(Node::get_right(node));
+        node
     }
 
     fn next_descending(&mut self) -> OpNode<K, V> {
-        loop {
-            let n = self.next_back_nodes.pop();
-            match n {
-                None => {
-                    if self.next_back_nodes.len() == 0 {
-                        break None;
-                    } else {
-                        continue;
-                    }
-                }
-                Some(node) => unsafe {
-                    let left = node
-                        .as_ref()
-                        .and_then(|n| (*n.as_ptr()).left_node)
-                        .filter(|n| !self.seen_back.contains(n));
-                    let right = node
-                        .as_ref()
-                        .and_then(|n| (*n.as_ptr()).right_node)
-                        .filter(|n| !self.seen_back.contains(n));
-
-                    if left.is_some() && right.is_some() {
-                        self.next_back_nodes.push(node);
-                        self.next_back_nodes.push(right);
-                        continue;
-                    } else if left.is_some() && right.is_none() {
-                        self.next_back_nodes.push(left);
-                        node.map(|n| {
-                            self.seen_back.insert(n);
-                        });
-                        break node;
-                    } else if left.is_none() && right.is_some() {
-                        self.next_back_nodes.push(node);
-                        self.next_back_nodes.push(right);
-                        continue;
-                    } else {
-                        // left is none and right is node
-                        node.map(|n| {
-                            self.seen.insert(n);
-                        });
-                        break node;
-                    }
-                },
-            }
+        if self.remaining == 0 {
+            return None;
         }
+        self.remaining -= 1;
+        let node = self.next_back_nodes.pop();
+        self.push_right_edge(Node::get_left(node));
+        node
     }
 }
 
@@ -842,6 +1207,10 @@ impl<'a, K: Ord, V> Iterator for Iter<'a, K, V> {
             .as_ref()
             .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
 }
 
 impl<'a, K: Ord, V> DoubleEndedIterator for Iter<'a, K, V> {
@@ -852,6 +1221,129 @@ impl<'a, K: Ord, V> DoubleEndedIterator for Iter<'a, K, V> {
     }
 }
 
+impl<'a, K: Ord, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+impl<'a, K: Ord, V> FusedIterator for Iter<'a, K, V> {}
+
+pub struct IterMut<'a, K: Ord, V> {
+    inner: Iter<'a, K, V>,
+    _marker: PhantomData<&'a mut Node<K, V>>,
+}
+
+impl<'a, K: Ord, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next_ascending()
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &mut (*n.as_ptr()).value) })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: Ord, V> ExactSizeIterator for IterMut<'a, K, V> {}
+
+impl<'a, K: Ord, V> FusedIterator for IterMut<'a, K, V> {}
+
+/// An iterator over the key-value pairs inside a range of keys
+pub struct Range<'a, K: Ord, V> {
+    front: OpNode<K, V>,
+    back: OpNode<K, V>,
+    _marker: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.front?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = Node::get_successor(self.front);
+        }
+        unsafe { Some((&(*node.as_ptr()).key, &(*node.as_ptr()).value)) }
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let node = self.back?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = Node::get_predecessor(self.back);
+        }
+        unsafe { Some((&(*node.as_ptr()).key, &(*node.as_ptr()).value)) }
+    }
+}
+
+/// An iterator which can peek both ends without advancing
+pub struct PeekingIter<'a, K: Ord, V> {
+    iter: Iter<'a, K, V>,
+    front: Option<(&'a K, &'a V)>,
+    back: Option<(&'a K, &'a V)>,
+    remaining: usize,
+}
+
+impl<'a, K: Ord, V> PeekingIter<'a, K, V> {
+    /// Peeking the next key-value pair from the front
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.front
+        }
+    }
+
+    /// Peeking the next key-value pair from the back
+    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.back
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for PeekingIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let out = self.front;
+        // the last pair may already be held by the back
+        self.front = if self.remaining > 0 {
+            self.iter.next().or(self.back)
+        } else {
+            None
+        };
+        out
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for PeekingIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let out = self.back;
+        self.back = if self.remaining > 0 {
+            self.iter.next_back().or(self.front)
+        } else {
+            None
+        };
+        out
+    }
+}
+
 impl<K: Ord, V> FromIterator<(K, V)> for AVL<K, V> {
     fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
         let inputs: Vec<_> = iter.into_iter().collect();
@@ -866,23 +1358,262 @@ impl<K: Ord, V> FromIterator<(K, V)> for AVL<K, V> {
     }
 }
 
-impl<K: Ord, V> IntoIterator for AVL<K, V> {
-    type Item = (K, V);
-    type IntoIter = IntoIter<K, V>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIter(self)
+impl<K: Ord, V, const N: usize> From<[(K, V); N]> for AVL<K, V> {
+    fn from(arr: [(K, V); N]) -> Self {
+        AVL::from_iter(arr)
     }
 }
 
-impl<K: Ord + Copy, V: Copy> Clone for AVL<K, V> {
+/// The pairs of a `BTreeMap` are already sorted
+/// so the tree is built without rebalancing
+impl<K: Ord, V> From<BTreeMap<K, V>> for AVL<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        AVL::from_sorted(map.into_iter().collect())
+    }
+}
+
+impl<K: Ord, V> From<AVL<K, V>> for BTreeMap<K, V> {
+    fn from(t: AVL<K, V>) -> Self {
+        t.into_iter().collect()
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for AVL<K, V> {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.add(k, v);
+        }
+    }
+}
+
+impl<'a, K: Ord + Copy, V: Copy> Extend<(&'a K, &'a V)> for AVL<K, V> {
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.add(*k, *v);
+        }
+    }
+}
+
+impl<K: Ord, V> Index<&K> for AVL<K, V> {
+    type Output = V;
+
+    /// Get the reference of value by key
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not in the tree
+    fn index(&self, k: &K) -> &V {
+        self.get(k).expect("key not found in the AVL")
+    }
+}
+
+impl<K: Ord, V> IntoIterator for AVL<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a AVL<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut AVL<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for AVL<K, V> {
     fn clone(&self) -> Self {
         let mut out = AVL::<K, V>::new();
         for (k, v) in self.iter() {
-            out.add(*k, *v);
+            out.add(k.clone(), v.clone());
+        }
+        out
+    }
+
+    /// The nodes of self are reused for the pairs of source
+    /// and linked again into a balanced tree, only the extra
+    /// nodes are allocated or freed
+    fn clone_from(&mut self, source: &Self) {
+        let mut nodes: Vec<OpNode<K, V>> = Vec::with_capacity(self.len.max(source.len));
+        let mut iter = self.iter();
+        while let Some(n) = iter.next_ascending() {
+            nodes.push(Some(n));
+        }
+        // the nodes are leaked instead of dropped twice if a clone panics
+        self.root_node = None;
+        self.len = 0;
+        for n in nodes.drain(source.len.min(nodes.len())..) {
+            Node::boxed_node(n);
+        }
+        for (n, (k, v)) in nodes.iter().zip(source.iter()) {
+            if let Some(n) = n {
+                unsafe {
+                    (*n.as_ptr()).key.clone_from(k);
+                    (*n.as_ptr()).value.clone_from(v);
+                }
+            }
+        }
+        let reused = nodes.len();
+        for (k, v) in source.iter().skip(reused) {
+            let node = Box::new(Node::new(k.clone(), v.clone()));
+            nodes.push(NonNull::new(Box::into_raw(node)));
+        }
+        let root = Self::_link_sorted(&nodes);
+        Node::set_parent(root, None);
+        self.root_node = root;
+        self.len = source.len;
+    }
+}
+
+impl<K: Ord, V> Default for AVL<K, V> {
+    fn default() -> Self {
+        AVL::new()
+    }
+}
+
+impl<K: Ord, V: PartialEq> PartialEq for AVL<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Ord, V: Eq> Eq for AVL<K, V> {}
+
+impl<K: Ord + Hash, V: Hash> Hash for AVL<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for (k, v) in self.iter() {
+            k.hash(state);
+            v.hash(state);
+        }
+    }
+}
+
+/// Serializing as a map in ascending key order
+#[cfg(feature = "serde")]
+impl<K: Ord + serde::Serialize, V: serde::Serialize> serde::Serialize for AVL<K, V> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Deserializing from a map by adding every pair into a new tree
+#[cfg(feature = "serde")]
+impl<'de, K, V> serde::Deserialize<'de> for AVL<K, V>
+where
+    K: Ord + serde::Deserialize<'de>,
+    V: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct AVLVisitor<K, V>(PhantomData<(K, V)>);
+
+        impl<'de, K, V> serde::de::Visitor<'de> for AVLVisitor<K, V>
+        where
+            K: Ord + serde::Deserialize<'de>,
+            V: serde::Deserialize<'de>,
+        {
+            type Value = AVL<K, V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<M: serde::de::MapAccess<'de>>(
+                self,
+                mut map: M,
+            ) -> Result<Self::Value, M::Error> {
+                let mut out = AVL::new();
+                while let Some((k, v)) = map.next_entry()? {
+                    out.add(k, v);
+                }
+                Ok(out)
+            }
+        }
+
+        deserializer.deserialize_map(AVLVisitor(PhantomData))
+    }
+}
+
+impl<K: Ord + fmt::Debug, V: fmt::Debug> fmt::Debug for AVL<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<K: Ord, V> AVL<K, V> {
+    /// Encoding the tree into a flat sorted binary layout
+    /// which is the number of entries followed by every key and value
+    /// in ascending order, each of them prefixed with it's length
+    ///
+    /// # Panics
+    ///
+    /// Panics if an encoded key or value is longer than `u32::MAX` bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 1);
+    /// let bytes = t.to_bytes(|k| k.to_le_bytes().to_vec(), |v| v.to_le_bytes().to_vec());
+    /// assert_eq!(bytes.len(), 8 + 2 * (4 + 4));
+    /// ```
+    pub fn to_bytes<FK, FV>(&self, encode_key: FK, encode_value: FV) -> Vec<u8>
+    where
+        FK: Fn(&K) -> Vec<u8>,
+        FV: Fn(&V) -> Vec<u8>,
+    {
+        let mut out = Vec::new();
+        bytes::write_u64(&mut out, self.len as u64);
+        for (k, v) in self.iter() {
+            bytes::write_chunk(&mut out, &encode_key(k));
+            bytes::write_chunk(&mut out, &encode_value(v));
         }
         out
     }
+
+    /// Decoding a tree from the layout written by `to_bytes`
+    /// Returns None if the bytes are malformed, any decoder fails
+    /// or the keys are not strictly ascending
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    /// use std::convert::TryInto;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 1);
+    /// let bytes = t.to_bytes(|k| k.to_le_bytes().to_vec(), |v| v.to_le_bytes().to_vec());
+    /// let decode = |b: &[u8]| b.try_into().ok().map(u32::from_le_bytes);
+    /// let t2 = AVL::from_bytes(&bytes, decode, decode).unwrap();
+    /// assert_eq!(t2.get(&1), Some(&1));
+    /// ```
+    pub fn from_bytes<FK, FV>(bytes: &[u8], decode_key: FK, decode_value: FV) -> Option<Self>
+    where
+        FK: Fn(&[u8]) -> Option<K>,
+        FV: Fn(&[u8]) -> Option<V>,
+    {
+        let mut reader = bytes::Reader::new(bytes);
+        let len = reader.read_u64()?;
+        let pairs = reader.read_sorted_pairs(len, decode_key, decode_value)?;
+        Some(AVL::from_sorted(pairs))
+    }
 }
 
 unsafe impl<K: Ord + Send, V: Send> Send for AVL<K, V> {}
@@ -893,6 +1624,18 @@ unsafe impl<K: Ord + Send, V: Send> Send for Iter<'_, K, V> {}
 
 unsafe impl<K: Ord + Sync, V: Sync> Sync for Iter<'_, K, V> {}
 
+unsafe impl<K: Ord + Send, V: Send> Send for PeekingIter<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for PeekingIter<'_, K, V> {}
+
+unsafe impl<K: Ord + Send, V: Send> Send for IterMut<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for IterMut<'_, K, V> {}
+
+unsafe impl<K: Ord + Send, V: Send> Send for Range<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for Range<'_, K, V> {}
+
 impl<K: Ord, V> AVL<K, V> {
     /// Create an empty AVL tree
     ///
@@ -990,19 +1733,90 @@ impl<K: Ord, V> AVL<K, V> {
     /// assert_eq!(iter.next_back(), Some((&2, &2)));
     /// ```
     pub fn iter<'a>(&'a self) -> Iter<'a, K, V> {
-        let nodes = vec![self.root_node];
-        let seen = HashSet::<NonNull<Node<K, V>>>::new();
-        let nodes_back = vec![self.root_node];
-        let seen_back = HashSet::<NonNull<Node<K, V>>>::new();
-        Iter {
-            next_nodes: nodes,
-            seen: seen,
-            next_back_nodes: nodes_back,
-            seen_back: seen_back,
+        Iter::new(self.root_node, self.len)
+    }
+
+    /// Provides an iterator of the keys in ascending order
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 10);
+    /// t.insert(1, 11);
+    /// t.insert(2, 12);
+    /// assert_eq!(t.keys().collect::<Vec<_>>(), vec![&0, &1, &2]);
+    /// assert_eq!(t.keys().rev().collect::<Vec<_>>(), vec![&2, &1, &0]);
+    /// ```
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Provides an iterator of the values in ascending order of keys
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 10);
+    /// t.insert(1, 11);
+    /// t.insert(2, 12);
+    /// assert_eq!(t.values().collect::<Vec<_>>(), vec![&10, &11, &12]);
+    /// assert_eq!(t.values().rev().collect::<Vec<_>>(), vec![&12, &11, &10]);
+    /// ```
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// Provides a forward iterator with mutable references of values
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    ///
+    /// for (_, v) in t.iter_mut() {
+    ///     *v *= 2;
+    /// }
+    /// assert_eq!(t.get(&2), Some(&4));
+    /// ```
+    pub fn iter_mut<'a>(&'a mut self) -> IterMut<'a, K, V> {
+        IterMut {
+            inner: self.iter(),
             _marker: PhantomData,
         }
     }
 
+    /// Provides a forward iterator of mutable references of values
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.values_mut().for_each(|v| *v = 7);
+    /// assert_eq!(t.get(&1), Some(&7));
+    /// ```
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
     /// Containment check
     ///
     /// # Example
@@ -1018,11 +1832,7 @@ impl<K: Ord, V> AVL<K, V> {
     /// assert!(t.contains(&1));
     /// ```
     pub fn contains(&self, k: &K) -> bool {
-        if self.is_empty() {
-            false
-        } else {
-            self.iter().any(|n| n.0.eq(k))
-        }
+        self._get_node(k).is_some()
     }
 
     /// Removing key-value pair
@@ -1065,7 +1875,7 @@ impl<K: Ord, V> AVL<K, V> {
             .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
     }
 
-    /// To check if shis tree is balanced
+    /// Peeking the node with the minimum key
     ///
     /// # Example
     ///
@@ -1077,13 +1887,15 @@ impl<K: Ord, V> AVL<K, V> {
     /// t.insert(0, 0);
     /// t.insert(1, 1);
     /// t.insert(2, 2);
-    /// assert_eq!(t.is_balanced_tree(), true);
+    /// assert_eq!(t.peek_min(), Some((&0, &0)));
     /// ```
-    pub fn is_balanced_tree(&self) -> bool {
-        self._is_balanced_tree()
+    pub fn peek_min(&self) -> Option<(&K, &V)> {
+        self._find_min_child(self.root_node)
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
     }
 
-    /// To check if shis tree is empty
+    /// Peeking the node with the maximum key
     ///
     /// # Example
     ///
@@ -1095,13 +1907,51 @@ impl<K: Ord, V> AVL<K, V> {
     /// t.insert(0, 0);
     /// t.insert(1, 1);
     /// t.insert(2, 2);
-    /// assert_eq!(t.is_empty(), false);
+    /// assert_eq!(t.peek_max(), Some((&2, &2)));
     /// ```
-    pub fn is_empty(&self) -> bool {
-        self.len == 0
+    pub fn peek_max(&self) -> Option<(&K, &V)> {
+        self._find_max_child(self.root_node)
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
     }
 
-    /// Removes all elements from the AVL tree
+    /// Getting the minimum key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// assert_eq!(t.first_key(), None);
+    ///
+    /// t.insert(1, 1);
+    /// t.insert(0, 0);
+    /// assert_eq!(t.first_key(), Some(&0));
+    /// ```
+    pub fn first_key(&self) -> Option<&K> {
+        self.peek_min().map(|(k, _)| k)
+    }
+
+    /// Getting the maximum key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// assert_eq!(t.last_key(), None);
+    ///
+    /// t.insert(1, 1);
+    /// t.insert(0, 0);
+    /// assert_eq!(t.last_key(), Some(&1));
+    /// ```
+    pub fn last_key(&self) -> Option<&K> {
+        self.peek_max().map(|(k, _)| k)
+    }
+
+    /// Poping out the minimum key-value pair in the tree
     ///
     /// # Example
     ///
@@ -1113,14 +1963,14 @@ impl<K: Ord, V> AVL<K, V> {
     /// t.insert(0, 0);
     /// t.insert(1, 1);
     /// t.insert(2, 2);
-    /// t.clear();
-    /// assert_eq!(t.len(), 0);
+    /// assert_eq!(t.pop_min(), Some((0, 0)));
+    /// assert_eq!(t.len(), 2);
     /// ```
-    pub fn clear(&mut self) {
-        *self = Self::new();
+    pub fn pop_min(&mut self) -> Option<(K, V)> {
+        self._pop_min().map(Node::into_element)
     }
 
-    /// Get the value by key
+    /// Poping out the maximum key-value pair in the tree
     ///
     /// # Example
     ///
@@ -1132,18 +1982,50 @@ impl<K: Ord, V> AVL<K, V> {
     /// t.insert(0, 0);
     /// t.insert(1, 1);
     /// t.insert(2, 2);
-    /// assert_eq!(t.get(&1), Some(&1));
+    /// assert_eq!(t.pop_max(), Some((2, 2)));
+    /// assert_eq!(t.len(), 2);
     /// ```
-    pub fn get(&self, k: &K) -> Option<&V> {
-        let mut outs: Vec<_> = self.iter().filter(|n| n.0.eq(k)).collect();
-        if outs.len() == 0 {
-            None
-        } else {
-            outs.pop().map(|o| o.1)
-        }
+    pub fn pop_max(&mut self) -> Option<(K, V)> {
+        self._pop_max().map(Node::into_element)
     }
 
-    /// Get a mutable reference of value by key
+    /// Poping out the first key-value pair, the AVL tree is a sorted map
+    /// so the first one has the smallest key, the same as `pop_min`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(1, 1);
+    /// t.insert(0, 0);
+    /// assert_eq!(t.pop_first(), Some((0, 0)));
+    /// ```
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        self.pop_min()
+    }
+
+    /// Poping out the last key-value pair, the AVL tree is a sorted map
+    /// so the last one has the largest key, the same as `pop_max`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(1, 1);
+    /// t.insert(0, 0);
+    /// assert_eq!(t.pop_last(), Some((1, 1)));
+    /// ```
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        self.pop_max()
+    }
+
+    /// To check if shis tree is balanced
     ///
     /// # Example
     ///
@@ -1151,14 +2033,1008 @@ impl<K: Ord, V> AVL<K, V> {
     /// use ABtree::AVL;
     ///
     /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
     /// t.insert(0, 0);
     /// t.insert(1, 1);
     /// t.insert(2, 2);
-    /// let v = t.get_mut(&2);
-    /// v.map(|i| *i += 10);
-    /// assert_eq!(t.get(&2), Some(&12))
-    /// ```    
-    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
-        self._get_mut(k)
+    /// assert_eq!(t.is_balanced_tree(), true);
+    /// ```
+    pub fn is_balanced_tree(&self) -> bool {
+        self._is_balanced_tree()
+    }
+
+    /// Checking the order of keys, the stored heights and sizes,
+    /// the balance factors and the parent links of all nodes,
+    /// reporting the first rule that is violated
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// for i in 0..100 {
+    ///     t.insert(i, i);
+    /// }
+    /// for i in (0..100).step_by(3) {
+    ///     t.remove(&i);
+    /// }
+    /// assert_eq!(t.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), AVLValidationError> {
+        self._validate()
+    }
+
+    /// To check if shis tree is empty
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// assert_eq!(t.is_empty(), false);
+    /// ```
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Removes all elements from the AVL tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// t.clear();
+    /// assert_eq!(t.len(), 0);
+    /// ```
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Get the value by key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// assert_eq!(t.get(&1), Some(&1));
+    /// ```
+    pub fn get(&self, k: &K) -> Option<&V> {
+        self._get_node(k).map(|n| unsafe { &(*n.as_ptr()).value })
+    }
+
+    /// Get a mutable reference of value by key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// let v = t.get_mut(&2);
+    /// v.map(|i| *i += 10);
+    /// assert_eq!(t.get(&2), Some(&12))
+    /// ```    
+    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
+        self._get_mut(k)
+    }
+
+    /// Get mutable references of the values of two different keys
+    /// at the same time, returns None if the keys are equal
+    /// or any of them is not in the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<&str, u32> = AVL::new();
+    /// t.insert("alice", 100);
+    /// t.insert("bob", 20);
+    /// if let Some((a, b)) = t.get_disjoint_mut(&"alice", &"bob") {
+    ///     *a -= 30;
+    ///     *b += 30;
+    /// }
+    /// assert_eq!(t.get(&"alice"), Some(&70));
+    /// assert_eq!(t.get(&"bob"), Some(&50));
+    /// assert_eq!(t.get_disjoint_mut(&"bob", &"bob"), None);
+    /// ```
+    pub fn get_disjoint_mut(&mut self, a: &K, b: &K) -> Option<(&mut V, &mut V)> {
+        if a == b {
+            return None;
+        }
+        let node_a = self._get_node(a)?;
+        let node_b = self._get_node(b)?;
+        // different keys are always in different nodes
+        unsafe { Some((&mut (*node_a.as_ptr()).value, &mut (*node_b.as_ptr()).value)) }
+    }
+
+    /// Applying `f` to the value if the key exists and returns true,
+    /// otherwise nothing happens and returns false
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(1, 1);
+    /// assert!(t.update(&1, |v| *v += 10));
+    /// assert!(!t.update(&2, |v| *v += 10));
+    /// assert_eq!(t.get(&1), Some(&11));
+    /// ```
+    pub fn update<F: FnOnce(&mut V)>(&mut self, k: &K, f: F) -> bool {
+        match self._get_node(k) {
+            None => false,
+            Some(n) => {
+                f(unsafe { &mut (*n.as_ptr()).value });
+                true
+            }
+        }
+    }
+
+    /// Removing all the keys of an ascending sorted slice
+    /// and returns the number of keys actually removed,
+    /// each key found is removed by it's own descent
+    /// so it takes O(m log n) time
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// assert_eq!(t.remove_sorted(&[0, 2, 5]), 2);
+    /// assert_eq!(t.len(), 1);
+    /// ```
+    pub fn remove_sorted(&mut self, keys: &[K]) -> usize {
+        debug_assert!(
+            keys.windows(2).all(|w| w[0] <= w[1]),
+            "the keys should be sorted in ascending order"
+        );
+        let mut removed: usize = 0;
+        for k in keys {
+            if Node::boxed_node(self._remove_node(k)).is_some() {
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Cloning the entries within a range into a new tree
+    /// and this tree will not be changed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// let c = t.clone_range(1..);
+    /// assert_eq!(c.len(), 2);
+    /// assert_eq!(t.len(), 3);
+    /// ```
+    pub fn clone_range<R: RangeBounds<K>>(&self, range: R) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
+        AVL::from_sorted(
+            self.range(range)
+                .map(|(k, v)| (k.clone(), v.clone()))
+                .collect(),
+        )
+    }
+
+    /// Adding key-value pair into the tree and returns the number
+    /// of nodes visited or modified, including the rotations
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    /// let mut t = AVL::<i32, i32>::new();
+    /// assert_eq!(t.insert_counting(2, 3), 1);
+    /// assert_eq!(t.insert_counting(3, 3), 2);
+    /// ```
+    pub fn insert_counting(&mut self, k: K, v: V) -> usize {
+        self._add_loop(k, v)
+    }
+
+    /// Grouping the keys by their values, it yields each distinct value
+    /// in ascending order together with the sorted keys mapping to it
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// t.insert(3, 1);
+    /// let groups: Vec<_> = t.group_by_value().collect();
+    /// assert_eq!(groups, vec![(&1, vec![&1, &3]), (&2, vec![&2])]);
+    /// ```
+    pub fn group_by_value(&self) -> impl Iterator<Item = (&V, Vec<&K>)>
+    where
+        V: Ord,
+    {
+        query::group_by_value(self.iter()).into_iter()
+    }
+
+    /// Get the entry which is `delta` positions away from a key
+    /// in sorted order, negative `delta` goes toward smaller keys
+    /// Returns None if the key is absent or the offset is out of range
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// t.insert(3, 3);
+    /// assert_eq!(t.offset_from(&1, 2), Some((&3, &3)));
+    /// assert_eq!(t.offset_from(&3, -1), Some((&2, &2)));
+    /// assert_eq!(t.offset_from(&3, 1), None);
+    /// ```
+    pub fn offset_from(&self, k: &K, delta: isize) -> Option<(&K, &V)> {
+        let pos = match self._count_less(k) {
+            (less, true) => less,
+            _ => return None,
+        };
+        self.select(pos.checked_add_signed(delta)?)
+    }
+
+    /// Iterating the entries in the order defined by a value comparator
+    /// Note all the entries are buffered and sorted before yielding
+    /// and entries with equal values stay in key order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 20);
+    /// t.insert(2, 30);
+    /// t.insert(3, 10);
+    /// let keys: Vec<_> = t.iter_by_value(|a, b| a.cmp(b)).map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, vec![3, 1, 2]);
+    /// ```
+    pub fn iter_by_value<F>(&self, mut cmp: F) -> impl Iterator<Item = (&K, &V)>
+    where
+        F: FnMut(&V, &V) -> Ordering,
+    {
+        let mut entries: Vec<(&K, &V)> = self.iter().collect();
+        entries.sort_by(|a, b| cmp(a.1, b.1));
+        entries.into_iter()
+    }
+
+    /// Provides an iterator which can peek both ends without advancing
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// t.insert(3, 3);
+    /// let mut iter = t.peekable_iter();
+    /// assert_eq!(iter.peek(), Some((&1, &1)));
+    /// assert_eq!(iter.peek_back(), Some((&3, &3)));
+    /// assert_eq!(iter.next(), Some((&1, &1)));
+    /// ```
+    pub fn peekable_iter(&self) -> PeekingIter<'_, K, V> {
+        let mut iter = self.iter();
+        let front = iter.next();
+        let back = iter.next_back().or(front);
+        PeekingIter {
+            iter,
+            front,
+            back,
+            remaining: self.len,
+        }
+    }
+
+    /// Get the length of the longest run of values in key order
+    /// where each value is strictly greater than the previous one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 3);
+    /// t.insert(2, 1);
+    /// t.insert(3, 2);
+    /// assert_eq!(t.longest_increasing_value_run(), 2);
+    /// ```
+    pub fn longest_increasing_value_run(&self) -> usize
+    where
+        V: PartialOrd,
+    {
+        let mut longest: usize = 0;
+        let mut cur_run: usize = 0;
+        let mut prev: Option<&V> = None;
+        for (_, v) in self.iter() {
+            cur_run = match prev {
+                Some(p) if v > p => cur_run + 1,
+                _ => 1,
+            };
+            if cur_run > longest {
+                longest = cur_run;
+            }
+            prev = Some(v);
+        }
+        longest
+    }
+
+    /// Removing up to n smallest key-value pairs
+    /// and returns them in ascending order
+    /// If n is not less than the length the tree will be drained
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// assert_eq!(t.pop_min_n(2), vec![(0, 0), (1, 1)]);
+    /// assert_eq!(t.len(), 1);
+    /// ```
+    pub fn pop_min_n(&mut self, n: usize) -> Vec<(K, V)> {
+        let mut out = Vec::with_capacity(n.min(self.len));
+        while out.len() < n {
+            match self._pop_min() {
+                None => {
+                    break;
+                }
+                Some(node) => out.push(Node::into_element(node)),
+            }
+        }
+        out
+    }
+
+    /// Provides an iterator of the key-value pairs in ascending order
+    /// together with the previous and the next key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// t.insert(3, 3);
+    /// let mut it = t.iter_with_neighbors();
+    /// assert_eq!(it.next(), Some((None, (&1, &1), Some(&2))));
+    /// assert_eq!(it.next(), Some((Some(&1), (&2, &2), Some(&3))));
+    /// assert_eq!(it.next(), Some((Some(&2), (&3, &3), None)));
+    /// assert_eq!(it.next(), None);
+    /// ```
+    pub fn iter_with_neighbors(&self) -> impl Iterator<Item = (Option<&K>, (&K, &V), Option<&K>)> {
+        let mut iter = self.iter().peekable();
+        let mut prev: Option<&K> = None;
+        core::iter::from_fn(move || {
+            let (k, v) = iter.next()?;
+            let next = iter.peek().map(|(nk, _)| *nk);
+            let out = (prev, (k, v), next);
+            prev = Some(k);
+            Some(out)
+        })
+    }
+
+    /// Provides an iterator of the key-value pairs whose keys
+    /// are inside the range in ascending order
+    /// An empty iterator is returned when the start is greater than the end
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// for i in 0..10 {
+    ///     t.insert(i, i * 10);
+    /// }
+    /// let keys: Vec<_> = t.range(3..6).map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, vec![3, 4, 5]);
+    /// let keys: Vec<_> = t.range(7..).rev().map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, vec![9, 8, 7]);
+    /// ```
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+        let front = self._lower_bound_node(range.start_bound());
+        let back = self._upper_bound_node(range.end_bound());
+        let is_empty = match (front, back) {
+            (Some(f), Some(b)) => unsafe { (*f.as_ptr()).key > (*b.as_ptr()).key },
+            _ => true,
+        };
+        if is_empty {
+            Range {
+                front: None,
+                back: None,
+                _marker: PhantomData,
+            }
+        } else {
+            Range {
+                front,
+                back,
+                _marker: PhantomData,
+            }
+        }
+    }
+
+    /// Keeping only the key-value pairs for which the predicate returns true
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// for i in 0..10 {
+    ///     t.insert(i, i);
+    /// }
+    /// t.retain(|k, _| k % 2 == 0);
+    /// assert_eq!(t.len(), 5);
+    /// assert!(t.is_balanced_tree());
+    /// ```
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        // collecting the nodes first so the walk is not broken by removals
+        let mut dropping: Vec<NonNull<Node<K, V>>> = Vec::new();
+        let mut iter = self.iter();
+        while let Some(n) = iter.next_ascending() {
+            let keep = unsafe { f(&(*n.as_ptr()).key, &mut (*n.as_ptr()).value) };
+            if !keep {
+                dropping.push(n);
+            }
+        }
+        for n in dropping {
+            let out = self._remove_node(unsafe { &(*n.as_ptr()).key });
+            Node::boxed_node(out);
+        }
+    }
+
+    /// Removing all the key-value pairs whose keys are in the range
+    /// and rebalancing the tree, which takes O(m * log n) time
+    /// for m removed pairs
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// for i in 0..10 {
+    ///     t.insert(i, i);
+    /// }
+    /// t.remove_range(2..8);
+    /// assert_eq!(t.len(), 4);
+    /// assert_eq!(t.get(&5), None);
+    /// assert!(t.is_balanced_tree());
+    /// ```
+    pub fn remove_range<R: RangeBounds<K>>(&mut self, range: R) {
+        while let Some(n) = self._lower_bound_node(range.start_bound()) {
+            let k = unsafe { &(*n.as_ptr()).key };
+            if !range.contains(k) {
+                break;
+            }
+            let out = self._remove_node(k);
+            Node::boxed_node(out);
+        }
+    }
+
+    /// Moving all the key-value pairs from other into self
+    /// and other becomes empty
+    /// The values from other win when the keys are the same
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// let mut other: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// other.insert(1, 10);
+    /// other.insert(2, 20);
+    /// t.append(&mut other);
+    /// assert_eq!(t.len(), 3);
+    /// assert_eq!(t.get(&1), Some(&10));
+    /// assert!(other.is_empty());
+    /// ```
+    pub fn append(&mut self, other: &mut AVL<K, V>) {
+        while let Some(node) = other._pop_min() {
+            let (k, v) = Node::into_element(node);
+            self.add(k, v);
+        }
+    }
+
+    /// Splitting the tree at a key, the key-value pairs whose keys
+    /// are greater or equal to the key are moved into a new tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// for i in 0..10 {
+    ///     t.insert(i, i);
+    /// }
+    /// let upper = t.split_off(&6);
+    /// assert_eq!(t.len(), 6);
+    /// assert_eq!(upper.len(), 4);
+    /// assert_eq!(upper.peek_min(), Some((&6, &6)));
+    /// ```
+    pub fn split_off(&mut self, k: &K) -> AVL<K, V> {
+        let mut out = AVL::new();
+        while self.peek_max().is_some_and(|(max_k, _)| max_k >= k) {
+            if let Some((max_k, max_v)) = self.pop_max() {
+                out.add(max_k, max_v);
+            }
+        }
+        out
+    }
+
+    /// Get the key-value pair with the largest key less than the given key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(2, 2);
+    /// t.insert(4, 4);
+    /// assert_eq!(t.predecessor(&2), Some((&0, &0)));
+    /// assert_eq!(t.predecessor(&3), Some((&2, &2)));
+    /// assert_eq!(t.predecessor(&0), None);
+    /// ```
+    pub fn predecessor(&self, k: &K) -> Option<(&K, &V)> {
+        self._upper_bound_node(Bound::Excluded(k))
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
+    }
+
+    /// Get the key-value pair with the smallest key greater than the given key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(2, 2);
+    /// t.insert(4, 4);
+    /// assert_eq!(t.successor(&2), Some((&4, &4)));
+    /// assert_eq!(t.successor(&1), Some((&2, &2)));
+    /// assert_eq!(t.successor(&4), None);
+    /// ```
+    pub fn successor(&self, k: &K) -> Option<(&K, &V)> {
+        self._lower_bound_node(Bound::Excluded(k))
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
+    }
+
+    /// Get the key-value pair with the largest key
+    /// less than or equal to the given key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(2, 2);
+    /// t.insert(4, 4);
+    /// assert_eq!(t.floor(&2), Some((&2, &2)));
+    /// assert_eq!(t.floor(&3), Some((&2, &2)));
+    /// ```
+    pub fn floor(&self, k: &K) -> Option<(&K, &V)> {
+        self._upper_bound_node(Bound::Included(k))
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
+    }
+
+    /// Get the key-value pair with the smallest key
+    /// greater than or equal to the given key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(2, 2);
+    /// t.insert(4, 4);
+    /// assert_eq!(t.ceiling(&2), Some((&2, &2)));
+    /// assert_eq!(t.ceiling(&3), Some((&4, &4)));
+    /// ```
+    pub fn ceiling(&self, k: &K) -> Option<(&K, &V)> {
+        self._lower_bound_node(Bound::Included(k))
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
+    }
+
+    /// Get the n-th smallest key-value pair, counting from 0
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(10, 0);
+    /// t.insert(20, 1);
+    /// t.insert(30, 2);
+    /// assert_eq!(t.select(1), Some((&20, &1)));
+    /// assert_eq!(t.select(3), None);
+    /// ```
+    pub fn select(&self, n: usize) -> Option<(&K, &V)> {
+        self._select_node(n)
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
+    }
+
+    /// Get the key-value pair at the given position in
+    /// sorted order, counting from 0
+    /// This is the same as `select` and takes O(log n) time
+    /// by the subtree sizes kept in each node
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let t: AVL<u32, u32> = (0..100).map(|i| (i * 10, i)).collect();
+    /// let page: Vec<_> = (20..25).filter_map(|i| t.nth(i)).collect();
+    /// assert_eq!(page[0], (&200, &20));
+    /// assert_eq!(t.nth(100), None);
+    /// ```
+    pub fn nth(&self, index: usize) -> Option<(&K, &V)> {
+        self.select(index)
+    }
+
+    /// Get the number of keys less than the given key
+    /// which does not have to be in the tree,
+    /// so it always returns Some
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(10, 0);
+    /// t.insert(20, 1);
+    /// t.insert(30, 2);
+    /// assert_eq!(t.rank(&30), Some(2));
+    /// assert_eq!(t.rank(&25), Some(2));
+    /// assert_eq!(t.rank(&40), Some(3));
+    /// ```
+    pub fn rank(&self, k: &K) -> Option<usize> {
+        Some(self._count_less(k).0)
+    }
+
+    /// Get the references of the stored key and the value by key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 10);
+    /// t.insert(1, 11);
+    /// assert_eq!(t.get_key_value(&1), Some((&1, &11)));
+    /// assert_eq!(t.get_key_value(&2), None);
+    /// ```
+    pub fn get_key_value(&self, k: &K) -> Option<(&K, &V)> {
+        self._get_node(k)
+            .as_ref()
+            .map(|n| unsafe { (&(*n.as_ptr()).key, &(*n.as_ptr()).value) })
+    }
+
+    /// Removing by key and returning the stored key and the value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 10);
+    /// t.insert(1, 11);
+    /// assert_eq!(t.remove_entry(&1), Some((1, 11)));
+    /// assert_eq!(t.remove_entry(&1), None);
+    /// assert_eq!(t.len(), 1);
+    /// ```
+    pub fn remove_entry(&mut self, k: &K) -> Option<(K, V)> {
+        let out = self._remove_node(k);
+        Node::boxed_node(out).map(Node::into_element)
+    }
+
+    /// Provides an iterator which pops out the key-value pairs
+    /// in ascending order, the tree becomes empty after the iterator
+    /// is dropped even if it's not fully consumed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// t.insert(2, 2);
+    /// assert_eq!(t.drain().next(), Some((0, 0)));
+    /// assert!(t.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        Drain(self)
+    }
+
+    /// Consuming the tree and returning all the
+    /// key-value pairs in ascending order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    ///
+    /// t.insert(2, 2);
+    /// t.insert(0, 0);
+    /// t.insert(1, 1);
+    /// assert_eq!(t.into_sorted_vec(), vec![(0, 0), (1, 1), (2, 2)]);
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut out = Vec::with_capacity(self.len);
+        out.extend(self);
+        out
+    }
+
+    /// Building a balanced tree from key-value pairs which
+    /// are sorted in ascending order without duplicated keys
+    /// in O(n) time and no rotations are needed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let t = AVL::from_sorted(vec![(0, 0), (1, 1), (2, 2)]);
+    /// assert_eq!(t.len(), 3);
+    /// assert_eq!(t.peek_root(), Some((&1, &1)));
+    /// ```
+    pub fn from_sorted(pairs: Vec<(K, V)>) -> Self {
+        debug_assert!(
+            pairs.windows(2).all(|w| w[0].0 < w[1].0),
+            "the pairs should be sorted by key without duplicates"
+        );
+        let len = pairs.len();
+        let nodes: Vec<OpNode<K, V>> = pairs
+            .into_iter()
+            .map(|(k, v)| NonNull::new(Box::into_raw(Box::new(Node::new(k, v)))))
+            .collect();
+        AVL {
+            root_node: Self::_link_sorted(&nodes),
+            len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Making a new tree with the keys in either tree,
+    /// the value of `other` wins if a key is in both trees
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let a = AVL::from_sorted(vec![(1, 'a'), (2, 'a')]);
+    /// let b = AVL::from_sorted(vec![(2, 'b'), (3, 'b')]);
+    /// let u = a.union(&b);
+    /// assert_eq!(u.into_sorted_vec(), vec![(1, 'a'), (2, 'b'), (3, 'b')]);
+    /// ```
+    pub fn union(&self, other: &AVL<K, V>) -> AVL<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        AVL::from_sorted(merge::merge_sorted(self.iter(), other.iter(), SetOp::Union))
+    }
+
+    /// Making a new tree with the keys in both trees
+    /// and the values are taken from `self`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let a = AVL::from_sorted(vec![(1, 'a'), (2, 'a')]);
+    /// let b = AVL::from_sorted(vec![(2, 'b'), (3, 'b')]);
+    /// let i = a.intersection(&b);
+    /// assert_eq!(i.into_sorted_vec(), vec![(2, 'a')]);
+    /// ```
+    pub fn intersection(&self, other: &AVL<K, V>) -> AVL<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        AVL::from_sorted(merge::merge_sorted(
+            self.iter(),
+            other.iter(),
+            SetOp::Intersection,
+        ))
+    }
+
+    /// Making a new tree with the keys in `self` but not in `other`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let a = AVL::from_sorted(vec![(1, 'a'), (2, 'a')]);
+    /// let b = AVL::from_sorted(vec![(2, 'b'), (3, 'b')]);
+    /// let d = a.difference(&b);
+    /// assert_eq!(d.into_sorted_vec(), vec![(1, 'a')]);
+    /// ```
+    pub fn difference(&self, other: &AVL<K, V>) -> AVL<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        AVL::from_sorted(merge::merge_sorted(
+            self.iter(),
+            other.iter(),
+            SetOp::Difference,
+        ))
+    }
+
+    /// Getting the mutable reference of the value by key,
+    /// if the key is not in the tree the value made by `f`
+    /// is inserted first
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// *t.get_or_insert_with(1, || 0) += 10;
+    /// *t.get_or_insert_with(1, || 0) += 10;
+    /// assert_eq!(t.get(&1), Some(&20));
+    /// ```
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, k: K, f: F) -> &mut V {
+        let node = self._get_or_add_node(k, f);
+        unsafe { &mut (*node.as_ptr()).value }
+    }
+
+    /// Counting the keys inside a range without visiting them,
+    /// it takes O(log n) time by the subtree sizes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let t = AVL::from_sorted((0..10).map(|i| (i, i)).collect());
+    /// assert_eq!(t.count_range(3..7), 4);
+    /// assert_eq!(t.count_range(..=3), 4);
+    /// assert_eq!(t.count_range(20..), 0);
+    /// ```
+    pub fn count_range<R: RangeBounds<K>>(&self, range: R) -> usize {
+        query::count_range(range, self.len, |k| self._count_less(k))
+    }
+
+    /// Exporting the shape of the tree as a Graphviz DOT graph,
+    /// each node is labeled with it's key and height and
+    /// the edges to the children are labeled with L and R
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::AVL;
+    ///
+    /// let mut t: AVL<u32, u32> = AVL::new();
+    /// t.insert(1, 1);
+    /// t.insert(0, 0);
+    /// t.insert(2, 2);
+    /// let dot = t.to_dot();
+    /// assert!(dot.starts_with("digraph AVL {"));
+    /// assert!(dot.contains("n0 [label=\"1\\nh=2\"];"));
+    /// assert!(dot.contains("n0 -> n1 [label=\"L\"];"));
+    /// ```
+    pub fn to_dot(&self) -> String
+    where
+        K: fmt::Display,
+    {
+        use core::fmt::Write;
+        let mut out = String::from("digraph AVL {\n");
+        let mut next_id: usize = 0;
+        let mut todo: Vec<(NonNull<Node<K, V>>, usize)> = Vec::new();
+        if let Some(root) = self.root_node {
+            todo.push((root, next_id));
+        }
+        while let Some((n, id)) = todo.pop() {
+            let node = unsafe { &*n.as_ptr() };
+            let key = node
+                .key
+                .to_string()
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"");
+            let _ = writeln!(out, "    n{} [label=\"{}\\nh={}\"];", id, key, node.height);
+            for (child, side) in [(node.left_node, "L"), (node.right_node, "R")] {
+                if let Some(c) = child {
+                    next_id += 1;
+                    let _ = writeln!(out, "    n{} -> n{} [label=\"{}\"];", id, next_id, side);
+                    todo.push((c, next_id));
+                }
+            }
+        }
+        out.push_str("}\n");
+        out
     }
 }
@@ -1,10 +1,20 @@
-use std::cell::Cell;
-use std::cmp::Ordering;
-use std::collections::{HashSet, VecDeque};
-use std::iter::FromIterator;
-use std::marker::PhantomData;
-use std::mem;
-use std::ptr::NonNull;
+#[cfg(feature = "bytes")]
+use crate::bytes;
+use crate::merge::{self, SetOp};
+use crate::query;
+use alloc::boxed::Box;
+use alloc::collections::{BTreeMap, VecDeque};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::iter::{FromIterator, FusedIterator};
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Bound, Index, RangeBounds};
+use core::ptr::NonNull;
 
 ///A b-tree with owned nodes
 ///and what makes it different from the BTreeMap in std
@@ -23,6 +33,7 @@ struct Node<K: Ord, V> {
     data: InnerData<K, V>,
     parent: OpNode<K, V>,
     children: Children<K, V>,
+    size: usize, // the number of keys in the subtree
 }
 
 struct Data<K: Ord, V> {
@@ -33,6 +44,88 @@ struct Data<K: Ord, V> {
 type OpNode<K: Ord, V> = Option<NonNull<Node<K, V>>>;
 type InnerData<K: Ord, V> = Option<NonNull<VecDeque<Data<K, V>>>>;
 type Children<K: Ord, V> = Option<NonNull<VecDeque<OpNode<K, V>>>>;
+type KeyBounds<'a, K> = (Option<&'a K>, Option<&'a K>);
+/// A node and the index of a key in it
+type KeyPos<K, V> = Option<(OpNode<K, V>, usize)>;
+
+/// The B-tree rule that is violated, reported by `BTree::validate`
+/// the depth of the root node is 0
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// The keys of a node are not in ascending order
+    /// or not within the range given by it's parent
+    UnsortedNode { depth: usize },
+    /// A node other than the root holds less keys than the minimum
+    UnderfullNode { depth: usize, keys: usize },
+    /// A node holds more keys than the maximum
+    OverfullNode { depth: usize, keys: usize },
+    /// An inner node does not have one more child than it's keys
+    ChildCountMismatch {
+        depth: usize,
+        keys: usize,
+        children: usize,
+    },
+    /// The stored subtree size of a node is not it's number of keys
+    /// plus the sizes of it's children
+    SizeMismatch {
+        depth: usize,
+        stored: usize,
+        expected: usize,
+    },
+    /// The leaves are not at the same depth
+    UnequalLeafDepth { expected: usize, found: usize },
+    /// A node does not point back to it's parent,
+    /// or the root has a parent
+    ParentMismatch { depth: usize },
+    /// The number of keys in the tree is not the stored length
+    LenMismatch { counted: usize, stored: usize },
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValidationError::UnsortedNode { depth } => {
+                write!(f, "unsorted keys in a node at depth {}", depth)
+            }
+            ValidationError::UnderfullNode { depth, keys } => {
+                write!(f, "underfull node at depth {} with {} keys", depth, keys)
+            }
+            ValidationError::OverfullNode { depth, keys } => {
+                write!(f, "overfull node at depth {} with {} keys", depth, keys)
+            }
+            ValidationError::ChildCountMismatch {
+                depth,
+                keys,
+                children,
+            } => write!(
+                f,
+                "node at depth {} has {} keys but {} children",
+                depth, keys, children
+            ),
+            ValidationError::SizeMismatch {
+                depth,
+                stored,
+                expected,
+            } => write!(
+                f,
+                "node at depth {} has size {} but expected {}",
+                depth, stored, expected
+            ),
+            ValidationError::UnequalLeafDepth { expected, found } => {
+                write!(f, "leaf at depth {} but expected {}", found, expected)
+            }
+            ValidationError::ParentMismatch { depth } => {
+                write!(f, "wrong parent pointer at depth {}", depth)
+            }
+            ValidationError::LenMismatch { counted, stored } => {
+                write!(f, "counted {} keys but the length is {}", counted, stored)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ValidationError {}
 
 impl<K: Ord, V> Node<K, V> {
     /// Get parent node
@@ -121,10 +214,33 @@ impl<K: Ord, V> Node<K, V> {
         }
     }
 
+    /// Get the number of keys in the subtree of a node
+    #[inline]
+    fn get_size(node: OpNode<K, V>) -> usize {
+        node.as_ref().map_or(0, |n| unsafe { (*n.as_ptr()).size })
+    }
+
+    /// Update the subtree size of a node
+    /// and the sizes of children must be updated
+    fn update_size(node: OpNode<K, V>) {
+        let children_size = Node::get_children_size(node);
+        let new_size = (0..children_size)
+            .map(|idx| Node::get_size(Node::get_child_by_index(node, idx)))
+            .sum::<usize>()
+            + Node::get_data_size(node);
+        if let Some(n) = node {
+            unsafe { (*n.as_ptr()).size = new_size };
+        }
+    }
+
     /// Give a node and key reutrn the index of key's position
     /// in the inner data
     #[inline]
-    fn get_key_index(node: OpNode<K, V>, k: &K) -> Option<usize> {
+    fn get_key_index<Q>(node: OpNode<K, V>, k: &Q) -> Option<usize>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let data = Node::get_inner_data(node);
         match data {
             None => None,
@@ -132,7 +248,7 @@ impl<K: Ord, V> Node<K, V> {
                 let iter = (*inner_d.as_ptr()).iter().enumerate();
                 let mut out: Option<usize> = None;
                 for (idx, d) in iter {
-                    if d.key.eq(k) {
+                    if d.key.borrow().cmp(k) == Ordering::Equal {
                         out = Some(idx);
                     }
                 }
@@ -205,7 +321,11 @@ impl<K: Ord, V> Node<K, V> {
 
     /// Given a node and key compare them and find a proper child
     /// If the key exists in the node it will return the node
-    fn moving_target(mut cur_node: OpNode<K, V>, k: &K) -> OpNode<K, V> {
+    fn moving_target<Q>(mut cur_node: OpNode<K, V>, k: &Q) -> OpNode<K, V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         'outer: loop {
             let inner_data = Node::get_inner_data(cur_node);
             let data_size = Node::get_data_size(cur_node);
@@ -220,7 +340,7 @@ impl<K: Ord, V> Node<K, V> {
                 Some(ref data) => unsafe {
                     let mut iter = (*data.as_ptr()).iter().enumerate();
                     'inner: while let Some((idx, x)) = iter.next() {
-                        let ordering = x.key.cmp(k);
+                        let ordering = x.key.borrow().cmp(k);
                         if idx == data_size - 1 {
                             match ordering {
                                 Ordering::Equal => {
@@ -299,6 +419,18 @@ impl<K: Ord, V> Node<K, V> {
         }
     }
 
+    /// Create a node holding the given Data without children
+    fn from_inner_data(data: VecDeque<Data<K, V>>) -> OpNode<K, V> {
+        let size = data.len();
+        let n = Box::new(Node {
+            data: NonNull::new(Box::into_raw(Box::new(data))),
+            parent: None,
+            children: None,
+            size,
+        });
+        NonNull::new(Box::into_raw(n))
+    }
+
     /// The caller must guarantee the `new_d` is not None
     /// this method will put new Data in a sorted place
     /// And if the key exists it will update the value
@@ -318,6 +450,7 @@ impl<K: Ord, V> Node<K, V> {
                     data: NonNull::new(Box::into_raw(d)),
                     parent: None,
                     children: None,
+                    size: 1,
                 });
                 NonNull::new(Box::into_raw(n))
             }
@@ -439,6 +572,9 @@ impl<K: Ord, V> Node<K, V> {
         Node::set_parent(right_node, node);
         Node::push_back_child(node, left_node);
         Node::push_back_child(node, right_node);
+        Node::update_size(left_node);
+        Node::update_size(right_node);
+        Node::update_size(node);
 
         node
     }
@@ -556,6 +692,54 @@ impl<K: Ord, V> Node<K, V> {
         }
     }
 
+    /// Given the position of a key return the position
+    /// of the next key in ascending order
+    fn next_position(node: OpNode<K, V>, idx: usize) -> KeyPos<K, V> {
+        if Node::get_children_size(node) != 0 {
+            let child = Node::get_child_by_index(node, idx + 1);
+            return Some((Node::get_minimum_node(child), 0));
+        }
+        if idx + 1 < Node::get_data_size(node) {
+            return Some((node, idx + 1));
+        }
+        // going up until the node is not the last child
+        let mut cur_node = node;
+        let mut parent = Node::get_parent(cur_node);
+        while parent.is_some() {
+            let pos = Node::get_child_position(parent, cur_node)?;
+            if pos < Node::get_data_size(parent) {
+                return Some((parent, pos));
+            }
+            cur_node = parent;
+            parent = Node::get_parent(cur_node);
+        }
+        None
+    }
+
+    /// Given the position of a key return the position
+    /// of the next key in descending order
+    fn prev_position(node: OpNode<K, V>, idx: usize) -> KeyPos<K, V> {
+        if Node::get_children_size(node) != 0 {
+            let child = Node::get_maximum_node(Node::get_child_by_index(node, idx));
+            return Some((child, Node::get_data_size(child) - 1));
+        }
+        if idx > 0 {
+            return Some((node, idx - 1));
+        }
+        // going up until the node is not the first child
+        let mut cur_node = node;
+        let mut parent = Node::get_parent(cur_node);
+        while parent.is_some() {
+            let pos = Node::get_child_position(parent, cur_node)?;
+            if pos > 0 {
+                return Some((parent, pos - 1));
+            }
+            cur_node = parent;
+            parent = Node::get_parent(cur_node);
+        }
+        None
+    }
+
     /// Use this methods for boxed a node when pop out a empty-node
     /// this method exists because empty Vec still holds some memory
     /// so turn them into a Box to drop the node
@@ -581,14 +765,18 @@ impl<K: Ord, V> Node<K, V> {
 
 impl<K: Ord, V> BTree<K, V> {
     /// adding key and value into tree
-    fn _add(&mut self, k: K, v: V) {
+    /// returns the number of nodes visited or modified
+    /// including the nodes created by splitting
+    fn _add(&mut self, k: K, v: V) -> usize {
         let mut cur_node = self.root_node;
+        let mut touched: usize = 0;
         loop {
             if cur_node.is_none() {
                 self.len += 1;
                 self.root_node = Node::adding_data(cur_node, Some(Data { key: k, value: v }), self);
-                break;
+                break touched + 1;
             }
+            touched += 1;
             let children_size = Node::get_children_size(cur_node);
             if children_size != 0 {
                 let cur_inner = Node::get_inner_data(cur_node);
@@ -598,7 +786,7 @@ impl<K: Ord, V> BTree<K, V> {
                 if contains_k == Some(true) {
                     self.len += 1; // because adding_data will minus 1 when equal happens
                     Node::adding_data(cur_node, Some(Data { key: k, value: v }), self);
-                    break;
+                    break touched;
                 } else {
                     cur_node = Node::moving_target(cur_node, &k);
                     continue;
@@ -606,36 +794,82 @@ impl<K: Ord, V> BTree<K, V> {
             } else {
                 self.len += 1;
                 let added_node = Node::adding_data(cur_node, Some(Data { key: k, value: v }), self);
-                self._up_merging(added_node);
-                break;
+                let splits = self._up_merging(added_node);
+                // each split creates a new left node and a new right node
+                break touched + splits * 2;
             }
         }
     }
 
     /// Recursively merging cur_node and it's parent if necessary
-    fn _up_merging(&mut self, mut cur_node: OpNode<K, V>) {
+    /// Returns the number of splits
+    fn _up_merging(&mut self, cur_node: OpNode<K, V>) -> usize {
+        self._up_merging_tracked(cur_node, None).0
+    }
+
+    /// The same as `_up_merging` but also following a key through
+    /// the splits, returns the number of splits and the new position of the key
+    fn _up_merging_tracked(
+        &mut self,
+        mut cur_node: OpNode<K, V>,
+        mut tracked: KeyPos<K, V>,
+    ) -> (usize, KeyPos<K, V>) {
+        let mut splits: usize = 0;
         loop {
             let data_size = Node::get_data_size(cur_node);
             let parent = Node::get_parent(cur_node);
             let parent_data_size = Node::get_data_size(parent);
             if data_size >= self.max_key_num {
-                let splitted_node = Node::split_node(cur_node, self.min_key_num, self);
+                let split_idx = self.min_key_num;
+                let splitted_node = Node::split_node(cur_node, split_idx, self);
+                splits += 1;
+                // the median stays in the splitted node
+                // and the halves become it's two children
+                let mut moved_up = false;
+                if let Some((node, idx)) = tracked.filter(|(n, _)| *n == cur_node) {
+                    tracked = match idx.cmp(&split_idx) {
+                        Ordering::Less => Some((Node::get_child_by_index(node, 0), idx)),
+                        Ordering::Equal => {
+                            moved_up = true;
+                            Some((node, 0))
+                        }
+                        Ordering::Greater => {
+                            Some((Node::get_child_by_index(node, 1), idx - split_idx - 1))
+                        }
+                    };
+                }
                 if parent.is_none() {
                     self.root_node = splitted_node;
-                    break;
-                } else if parent_data_size + 1 < self.max_key_num {
+                    break (splits, tracked);
+                }
+                // the median is merged into the parent
+                // at the position of the splitted node
+                if moved_up {
+                    tracked = Node::get_child_position(parent, splitted_node).map(|i| (parent, i));
+                }
+                if parent_data_size + 1 < self.max_key_num {
                     Node::merging_nodes(parent, splitted_node);
-                    break;
-                } else if parent_data_size + 1 >= self.max_key_num {
+                    self._update_sizes_up(parent);
+                    break (splits, tracked);
+                } else {
                     cur_node = Node::merging_nodes(parent, splitted_node);
                     continue;
                 }
             } else {
-                break;
+                self._update_sizes_up(cur_node);
+                break (splits, tracked);
             }
         }
     }
 
+    /// Updating the subtree sizes from a changed node up to the root
+    fn _update_sizes_up(&mut self, mut cur_node: OpNode<K, V>) {
+        while cur_node.is_some() {
+            Node::update_size(cur_node);
+            cur_node = Node::get_parent(cur_node);
+        }
+    }
+
     /// The input node could be unbalanced which data size is less than
     /// self.min_key_num
     /// And removing a key could make some node unbalanced
@@ -656,6 +890,7 @@ impl<K: Ord, V> BTree<K, V> {
             // if cur_node is the only one node in the tree
             // or it is balanced then just returns
             if parent.is_none() || data_size >= self.min_key_num {
+                self._update_sizes_up(cur_node);
                 break;
             }
             let rich_sibling = Node::get_rich_siblings(cur_node, self.min_key_num);
@@ -692,6 +927,7 @@ impl<K: Ord, V> BTree<K, V> {
                                 Node::set_parent(cur_c, next_sibling);
                                 nc -= 1;
                             }
+                            Node::update_size(next_sibling);
                             let empty_node = Node::remove_child(parent, cur_c_idx);
                             let _empty_node = Node::into_boxed(empty_node);
                             cur_node = parent;
@@ -715,6 +951,7 @@ impl<K: Ord, V> BTree<K, V> {
                                 Node::set_parent(cur_c, prev_sibling);
                                 nc -= 1;
                             }
+                            Node::update_size(prev_sibling);
                             let empty_node = Node::remove_child(parent, cur_c_idx);
                             let _empty_node = Node::into_boxed(empty_node);
                             cur_node = parent;
@@ -737,6 +974,9 @@ impl<K: Ord, V> BTree<K, V> {
                                 Node::push_back_child(cur_node, sibling_out_child);
                                 Node::set_parent(sibling_out_child, cur_node);
                             }
+                            Node::update_size(cur_node);
+                            Node::update_size(rich_sibling);
+                            self._update_sizes_up(parent);
                             break;
                         } else {
                             let parent_out_data = Node::remove_data(parent, cur_idx - 1);
@@ -749,11 +989,14 @@ impl<K: Ord, V> BTree<K, V> {
                             if Node::get_children_size(rich_sibling) != 0 {
                                 let sibling_out_child = Node::remove_child(
                                     rich_sibling,
-                                    Node::get_data_size(rich_sibling) - 1,
+                                    Node::get_children_size(rich_sibling) - 1,
                                 );
                                 Node::push_front_child(cur_node, sibling_out_child);
                                 Node::set_parent(sibling_out_child, cur_node);
                             }
+                            Node::update_size(cur_node);
+                            Node::update_size(rich_sibling);
+                            self._update_sizes_up(parent);
                             break;
                         }
                     }
@@ -813,24 +1056,31 @@ impl<K: Ord, V> BTree<K, V> {
 
     /// Give a ref of key return value
     #[inline]
-    fn _get(&self, k: &K) -> Option<&V> {
+    fn _get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self._get_key_value(k).map(|(_, v)| v)
+    }
+
+    /// Give a ref of key return the stored key and value
+    #[inline]
+    fn _get_key_value<Q>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let node = Node::moving_target(self.root_node, k);
-        let inner_data = Node::get_inner_data(node);
-        match inner_data {
-            None => None,
-            Some(ref data) => unsafe {
-                let mut iter = (*data.as_ptr()).iter();
-                while let Some(d) = iter.next() {
-                    if d.key.eq(k) {
-                        return Some(&d.value);
-                    }
-                }
-                None
-            },
-        }
+        let idx = Node::get_key_index(node, k)?;
+        self._key_value_at(Some((node, idx)))
     }
 
-    fn _get_mut(&mut self, k: &K) -> Option<&mut V> {
+    fn _get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let node = Node::moving_target(self.root_node, k);
         let inner_data = Node::get_inner_data(node);
         match inner_data {
@@ -838,7 +1088,7 @@ impl<K: Ord, V> BTree<K, V> {
             Some(ref data) => unsafe {
                 let mut iter_mut = (*data.as_ptr()).iter_mut();
                 while let Some(d) = iter_mut.next() {
-                    if d.key.eq(k) {
+                    if d.key.borrow().cmp(k) == Ordering::Equal {
                         return Some(&mut d.value);
                     }
                 }
@@ -847,67 +1097,408 @@ impl<K: Ord, V> BTree<K, V> {
         }
     }
 
-    /// removing by key
-    fn _remove(&mut self, k: &K) -> Option<V> {
+    /// Walking through all the nodes to check the B-tree rules
+    fn _validate(&self) -> Result<(), ValidationError> {
+        // a full node splits at self.min_key_num and for an even order
+        // the right half gets one key less, and removing keys only
+        // rebalances the nodes which go below that
+        let min_keys = self.max_key_num.div_ceil(2) - 1;
+        let mut counted: usize = 0;
+        let mut leaf_depth: Option<usize> = None;
+        if Node::get_parent(self.root_node).is_some() {
+            return Err(ValidationError::ParentMismatch { depth: 0 });
+        }
+        // each node is visited with it's depth and the range of keys
+        // given by the parent
+        let mut todo: Vec<(OpNode<K, V>, usize, KeyBounds<K>)> =
+            vec![(self.root_node, 0, (None, None))];
+        while let Some((cur_node, depth, (low, high))) = todo.pop() {
+            let data = match Node::get_inner_data(cur_node) {
+                None => {
+                    continue;
+                }
+                Some(d) => unsafe { &*d.as_ptr() },
+            };
+            let keys = data.len();
+            let children = Node::get_children_size(cur_node);
+            counted += keys;
+            for (idx, d) in data.iter().enumerate() {
+                let out_of_order = idx > 0 && data[idx - 1].key >= d.key;
+                let out_of_range =
+                    low.is_some_and(|l| d.key <= *l) || high.is_some_and(|h| d.key >= *h);
+                if out_of_order || out_of_range {
+                    return Err(ValidationError::UnsortedNode { depth });
+                }
+            }
+            if depth > 0 && keys < min_keys {
+                return Err(ValidationError::UnderfullNode { depth, keys });
+            }
+            if keys > self.max_key_num {
+                return Err(ValidationError::OverfullNode { depth, keys });
+            }
+            if children == 0 {
+                match leaf_depth {
+                    None => leaf_depth = Some(depth),
+                    Some(expected) if expected != depth => {
+                        return Err(ValidationError::UnequalLeafDepth {
+                            expected,
+                            found: depth,
+                        });
+                    }
+                    _ => {}
+                }
+            } else if children != keys + 1 {
+                return Err(ValidationError::ChildCountMismatch {
+                    depth,
+                    keys,
+                    children,
+                });
+            } else {
+                for idx in 0..children {
+                    let child_low = if idx == 0 {
+                        low
+                    } else {
+                        Some(&data[idx - 1].key)
+                    };
+                    let child_high = if idx == keys {
+                        high
+                    } else {
+                        Some(&data[idx].key)
+                    };
+                    let child = Node::get_child_by_index(cur_node, idx);
+                    if Node::get_parent(child) != cur_node {
+                        return Err(ValidationError::ParentMismatch { depth: depth + 1 });
+                    }
+                    todo.push((child, depth + 1, (child_low, child_high)));
+                }
+            }
+            let expected = keys
+                + (0..children)
+                    .map(|idx| Node::get_size(Node::get_child_by_index(cur_node, idx)))
+                    .sum::<usize>();
+            let stored = Node::get_size(cur_node);
+            if stored != expected {
+                return Err(ValidationError::SizeMismatch {
+                    depth,
+                    stored,
+                    expected,
+                });
+            }
+        }
+        if counted != self.len {
+            return Err(ValidationError::LenMismatch {
+                counted,
+                stored: self.len,
+            });
+        }
+        Ok(())
+    }
+
+    /// Find the position of the minimum key inside the lower bound
+    fn _lower_bound_pos(&self, bound: Bound<&K>) -> KeyPos<K, V> {
+        let mut cur_node = self.root_node;
+        let mut found: KeyPos<K, V> = None;
+        while let Some(data) = Node::get_inner_data(cur_node) {
+            let data = unsafe { &*data.as_ptr() };
+            // the number of keys outside the bound
+            let idx = data
+                .iter()
+                .take_while(|d| match bound {
+                    Bound::Included(start) => d.key < *start,
+                    Bound::Excluded(start) => d.key <= *start,
+                    Bound::Unbounded => false,
+                })
+                .count();
+            if idx < data.len() {
+                found = Some((cur_node, idx));
+            }
+            cur_node = Node::get_child_by_index(cur_node, idx);
+        }
+        found
+    }
+
+    /// Find the position of the maximum key inside the upper bound
+    fn _upper_bound_pos(&self, bound: Bound<&K>) -> KeyPos<K, V> {
+        let mut cur_node = self.root_node;
+        let mut found: KeyPos<K, V> = None;
+        while let Some(data) = Node::get_inner_data(cur_node) {
+            let data = unsafe { &*data.as_ptr() };
+            // the number of keys inside the bound
+            let idx = data
+                .iter()
+                .take_while(|d| match bound {
+                    Bound::Included(end) => d.key <= *end,
+                    Bound::Excluded(end) => d.key < *end,
+                    Bound::Unbounded => true,
+                })
+                .count();
+            if idx > 0 {
+                found = Some((cur_node, idx - 1));
+            }
+            cur_node = Node::get_child_by_index(cur_node, idx);
+        }
+        found
+    }
+
+    /// Turn a position into references of key and value
+    fn _key_value_at(&self, pos: KeyPos<K, V>) -> Option<(&K, &V)> {
+        let (node, idx) = pos?;
+        Node::get_inner_data(node).map(|data| {
+            let d = unsafe { &(&*data.as_ptr())[idx] };
+            (&d.key, &d.value)
+        })
+    }
+
+    /// Finding the position of the n-th smallest key
+    /// with the subtree sizes
+    fn _select_pos(&self, mut n: usize) -> KeyPos<K, V> {
+        if n >= self.len {
+            return None;
+        }
+        let mut cur_node = self.root_node;
+        'outer: loop {
+            let data_size = Node::get_data_size(cur_node);
+            for idx in 0..data_size {
+                let child_size = Node::get_size(Node::get_child_by_index(cur_node, idx));
+                if n < child_size {
+                    cur_node = Node::get_child_by_index(cur_node, idx);
+                    continue 'outer;
+                }
+                n -= child_size;
+                if n == 0 {
+                    break 'outer Some((cur_node, idx));
+                }
+                n -= 1;
+            }
+            cur_node = Node::get_child_by_index(cur_node, data_size);
+            if cur_node.is_none() {
+                break None;
+            }
+        }
+    }
+
+    /// Counting the keys less than the given key
+    /// and telling if the key is in the tree
+    fn _count_less(&self, k: &K) -> (usize, bool) {
+        let mut cur_node = self.root_node;
+        let mut less: usize = 0;
+        while let Some(data) = Node::get_inner_data(cur_node) {
+            let data = unsafe { &*data.as_ptr() };
+            let mut next_idx = data.len();
+            for (idx, d) in data.iter().enumerate() {
+                let child_size = Node::get_size(Node::get_child_by_index(cur_node, idx));
+                match d.key.cmp(k) {
+                    Ordering::Less => {
+                        less += child_size + 1;
+                    }
+                    Ordering::Equal => {
+                        return (less + child_size, true);
+                    }
+                    Ordering::Greater => {
+                        next_idx = idx;
+                        break;
+                    }
+                }
+            }
+            cur_node = Node::get_child_by_index(cur_node, next_idx);
+        }
+        (less, false)
+    }
+
+    /// Counting all the nodes in the tree
+    fn _node_count(&self) -> usize {
+        let mut count: usize = 0;
+        let mut todo = vec![self.root_node];
+        while let Some(cur_node) = todo.pop() {
+            if cur_node.is_none() {
+                continue;
+            }
+            count += 1;
+            let children_size = Node::get_children_size(cur_node);
+            for idx in 0..children_size {
+                todo.push(Node::get_child_by_index(cur_node, idx));
+            }
+        }
+        count
+    }
+
+    /// Packing sorted Data into nodes level by level from the leaves,
+    /// every level is split into as few nodes as possible with the keys
+    /// spread evenly and the Data between two nodes goes up a level
+    /// Returns the root node
+    fn _pack_sorted(&self, mut items: VecDeque<Data<K, V>>) -> OpNode<K, V> {
+        let mut children: VecDeque<OpNode<K, V>> = VecDeque::new();
+        loop {
+            let total = items.len();
+            let node_num = if total <= self.max_key_num {
+                1
+            } else {
+                (total + 1).div_ceil(self.max_key_num + 1)
+            };
+            let key_num = total - (node_num - 1);
+            let mut nodes = VecDeque::with_capacity(node_num);
+            let mut upper_items = VecDeque::with_capacity(node_num - 1);
+            for i in 0..node_num {
+                let cnt = key_num / node_num + usize::from(i < key_num % node_num);
+                let node = Node::from_inner_data(items.drain(..cnt).collect());
+                if !children.is_empty() {
+                    for child in children.drain(..cnt + 1) {
+                        Node::set_parent(child, node);
+                        Node::push_back_child(node, child);
+                    }
+                }
+                Node::update_size(node);
+                nodes.push_back(node);
+                if i + 1 < node_num {
+                    upper_items.push_back(items.pop_front().unwrap());
+                }
+            }
+            if node_num == 1 {
+                break nodes.pop_front().flatten();
+            }
+            items = upper_items;
+            children = nodes;
+        }
+    }
+
+    /// Counting the levels of the tree
+    /// all the leaves are at the same depth
+    fn _height(&self) -> usize {
+        let mut height: usize = 0;
+        let mut cur_node = self.root_node;
+        while cur_node.is_some() {
+            height += 1;
+            cur_node = Node::get_child_by_index(cur_node, 0);
+        }
+        height
+    }
+
+    /// removing by key and returning the removed Data
+    fn _remove<Q>(&mut self, k: &Q) -> Option<Data<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
         let node = Node::moving_target(self.root_node, k);
+        Node::get_key_index(node, k).and_then(|idx| self._remove_at(node, idx))
+    }
+
+    /// Removing the key at the given index of a node
+    /// and rebalancing the tree
+    fn _remove_at(&mut self, node: OpNode<K, V>, idx: usize) -> Option<Data<K, V>> {
         let parent = Node::get_parent(node);
-        let target_idx = Node::get_key_index(node, k);
-        match target_idx {
-            None => None,
-            Some(idx) => {
-                if self.len == 1 {
-                    self.len = 0;
-                    self.root_node = None;
-                    Node::get_inner_data(node)
-                        .and_then(|d| Node::pop_front_inner_data(Some(d)))
-                        .map(|o| o.value)
+        if self.len == 1 {
+            self.len = 0;
+            self.root_node = None;
+            Node::get_inner_data(node).and_then(|d| Node::pop_front_inner_data(Some(d)))
+        } else {
+            self.len -= 1;
+            let left_child = Node::get_child_by_index(node, idx);
+            let right_child = Node::get_child_by_index(node, idx + 1);
+            let no_children = left_child.is_none();
+
+            if !no_children {
+                let left_max = Node::get_maximum_node(left_child);
+                let right_min = Node::get_minimum_node(right_child);
+                let left_max_is_rich = Node::get_data_size(left_max) > self.min_key_num;
+                let right_min_is_rich = Node::get_data_size(right_min) > self.min_key_num;
+                let removed_out = Node::remove_data(node, idx);
+                if left_max_is_rich {
+                    let replace_data =
+                        Node::remove_data(left_max, Node::get_data_size(left_max) - 1);
+                    Node::insert_data(node, idx, replace_data);
+                    self._update_sizes_up(left_max);
+                    removed_out
+                } else if right_min_is_rich {
+                    let replace_data = Node::remove_data(right_min, 0);
+                    Node::insert_data(node, idx, replace_data);
+                    self._update_sizes_up(right_min);
+                    removed_out
                 } else {
-                    self.len -= 1;
-                    let left_child = Node::get_child_by_index(node, idx);
-                    let right_child = Node::get_child_by_index(node, idx + 1);
-                    let no_children = left_child == None;
-
-                    if !no_children {
-                        let left_max = Node::get_maximum_node(left_child);
-                        let right_min = Node::get_minimum_node(right_child);
-                        let left_max_is_rich = Node::get_data_size(left_max) > self.min_key_num;
-                        let right_min_is_rich = Node::get_data_size(right_min) > self.min_key_num;
-                        let removed_out = Node::remove_data(node, idx);
-                        if left_max_is_rich {
-                            let replace_data =
-                                Node::remove_data(left_max, Node::get_data_size(left_max) - 1);
-                            Node::insert_data(node, idx, replace_data);
-                            removed_out.map(|n| n.value)
-                        } else if right_min_is_rich {
-                            let replace_data = Node::remove_data(right_min, 0);
-                            Node::insert_data(node, idx, replace_data);
-                            removed_out.map(|n| n.value)
-                        } else {
-                            let replace_data =
-                                Node::remove_data(left_max, Node::get_data_size(left_max) - 1);
-                            Node::insert_data(node, idx, replace_data);
-                            self._rebalancing(left_max);
-                            removed_out.map(|n| n.value)
-                        }
+                    let replace_data =
+                        Node::remove_data(left_max, Node::get_data_size(left_max) - 1);
+                    Node::insert_data(node, idx, replace_data);
+                    self._rebalancing(left_max);
+                    removed_out
+                }
+            } else {
+                let removed_out = Node::remove_data(node, idx);
+                if parent.is_some() {
+                    if Node::get_data_size(node) < self.min_key_num {
+                        self._rebalancing(node);
+                        removed_out
                     } else {
-                        let removed_out = Node::remove_data(node, idx);
-                        if parent.is_some() {
-                            if Node::get_data_size(node) < self.min_key_num {
-                                self._rebalancing(node);
-                                removed_out.map(|d| d.value)
-                            } else {
-                                removed_out.map(|d| d.value)
-                            }
-                        } else {
-                            removed_out.map(|d| d.value)
-                        }
+                        self._update_sizes_up(node);
+                        removed_out
                     }
+                } else {
+                    self._update_sizes_up(node);
+                    removed_out
                 }
             }
         }
     }
 }
 
+/// Hooks for breaking the B-tree rules in tests
+#[cfg(test)]
+impl<K: Ord, V> BTree<K, V> {
+    pub(crate) fn _test_set_len(&mut self, len: usize) {
+        self.len = len;
+    }
+
+    pub(crate) fn _test_set_root_size(&mut self, size: usize) {
+        if let Some(n) = self.root_node {
+            unsafe { (*n.as_ptr()).size = size }
+        }
+    }
+
+    pub(crate) fn _test_swap_root_keys(&mut self, i: usize, j: usize) {
+        if let Some(d) = Node::get_inner_data(self.root_node) {
+            unsafe { (*d.as_ptr()).swap(i, j) }
+        }
+    }
+
+    /// Removing the first key of the minimum leaf without rebalancing
+    pub(crate) fn _test_pop_min_leaf_key(&mut self) -> Option<(K, V)> {
+        let min_node = Node::get_minimum_node(self.root_node);
+        let out = Node::pop_front_inner_data(Node::get_inner_data(min_node));
+        self.len -= 1;
+        out.map(|d| (d.key, d.value))
+    }
+
+    /// Pushing a key into the root node without a new child
+    pub(crate) fn _test_push_root_key(&mut self, k: K, v: V) {
+        self.len += 1;
+        Node::push_back_inner_data(self.root_node, Some(Data { key: k, value: v }));
+    }
+
+    /// Replacing the last child of root by it's own last child
+    pub(crate) fn _test_lift_last_grandchild(&mut self) {
+        let root = self.root_node;
+        let last_idx = Node::get_children_size(root) - 1;
+        let child = Node::get_child_by_index(root, last_idx);
+        let grandchild = Node::get_child_by_index(child, Node::get_children_size(child) - 1);
+        Node::remove_child(root, last_idx);
+        Node::push_back_child(root, grandchild);
+        Node::set_parent(grandchild, root);
+        Node::update_size(root);
+    }
+
+    /// Pointing the parent of the first child of root to itself
+    pub(crate) fn _test_break_first_child_parent(&mut self) {
+        let child = Node::get_child_by_index(self.root_node, 0);
+        Node::set_parent(child, child);
+    }
+
+    /// Pushing a key into the minimum leaf without splitting
+    pub(crate) fn _test_push_min_leaf_key(&mut self, k: K, v: V) {
+        let min_node = Node::get_minimum_node(self.root_node);
+        self.len += 1;
+        Node::push_front_inner_data(min_node, Some(Data { key: k, value: v }));
+    }
+}
+
 pub struct IntoIter<K: Ord, V>(BTree<K, V>);
 
 impl<K: Ord, V> Iterator for IntoIter<K, V> {
@@ -915,6 +1506,10 @@ impl<K: Ord, V> Iterator for IntoIter<K, V> {
     fn next(&mut self) -> Option<Self::Item> {
         self.0._pop_min_data().map(|d| (d.key, d.value))
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.0.len, Some(self.0.len))
+    }
 }
 
 impl<K: Ord, V> DoubleEndedIterator for IntoIter<K, V> {
@@ -923,6 +1518,10 @@ impl<K: Ord, V> DoubleEndedIterator for IntoIter<K, V> {
     }
 }
 
+impl<K: Ord, V> ExactSizeIterator for IntoIter<K, V> {}
+
+impl<K: Ord, V> FusedIterator for IntoIter<K, V> {}
+
 impl<K: Ord, V> Drop for IntoIter<K, V> {
     fn drop(&mut self) {
         struct DropGuard<'a, K: Ord, V>(&'a mut IntoIter<K, V>);
@@ -941,6 +1540,94 @@ impl<K: Ord, V> Drop for IntoIter<K, V> {
     }
 }
 
+/// A view into a key-value pair in the tree, given by
+/// `BTree::first_entry` and `BTree::last_entry`
+pub struct OccupiedEntry<'a, K: Ord, V> {
+    tree: &'a mut BTree<K, V>,
+    node: OpNode<K, V>,
+    idx: usize,
+}
+
+impl<'a, K: Ord, V> OccupiedEntry<'a, K, V> {
+    fn inner_data(&self) -> NonNull<VecDeque<Data<K, V>>> {
+        Node::get_inner_data(self.node).expect("the entry should be in a node")
+    }
+
+    /// Get a reference of the key
+    pub fn key(&self) -> &K {
+        unsafe { &(&*self.inner_data().as_ptr())[self.idx].key }
+    }
+
+    /// Get a reference of the value
+    pub fn get(&self) -> &V {
+        unsafe { &(&*self.inner_data().as_ptr())[self.idx].value }
+    }
+
+    /// Get a mutable reference of the value
+    pub fn get_mut(&mut self) -> &mut V {
+        unsafe { &mut (&mut *self.inner_data().as_ptr())[self.idx].value }
+    }
+
+    /// Turning the entry into a mutable reference of the value
+    /// which lives as long as the tree is borrowed
+    pub fn into_mut(self) -> &'a mut V {
+        unsafe { &mut (&mut *self.inner_data().as_ptr())[self.idx].value }
+    }
+
+    /// Setting the value and returning the old one
+    pub fn insert(&mut self, v: V) -> V {
+        mem::replace(self.get_mut(), v)
+    }
+
+    /// Removing the pair from the tree and returning it
+    pub fn remove_entry(self) -> (K, V) {
+        let d = self
+            .tree
+            ._remove_at(self.node, self.idx)
+            .expect("the entry should be in the tree");
+        (d.key, d.value)
+    }
+
+    /// Removing the pair from the tree and returning the value
+    pub fn remove(self) -> V {
+        self.remove_entry().1
+    }
+}
+
+/// A draining iterator which leaves the tree empty when dropped
+pub struct Drain<'a, K: Ord, V>(&'a mut BTree<K, V>);
+
+impl<'a, K: Ord, V> Iterator for Drain<'a, K, V> {
+    type Item = (K, V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.pop_min()
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for Drain<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.0.pop_max()
+    }
+}
+
+impl<'a, K: Ord, V> Drop for Drain<'a, K, V> {
+    fn drop(&mut self) {
+        struct DropGuard<'b, 'a, K: Ord, V>(&'b mut Drain<'a, K, V>);
+
+        impl<'b, 'a, K: Ord, V> Drop for DropGuard<'b, 'a, K, V> {
+            fn drop(&mut self) {
+                while self.0.next().is_some() {}
+            }
+        }
+
+        while let Some(d) = self.next() {
+            let guard = DropGuard(self);
+            drop(d);
+            mem::forget(guard);
+        }
+    }
+}
+
 impl<K: Ord, V> Drop for BTree<K, V> {
     fn drop(&mut self) {
         struct DropGuard<'a, K: Ord, V>(&'a mut BTree<K, V>);
@@ -959,330 +1646,1680 @@ impl<K: Ord, V> Drop for BTree<K, V> {
     }
 }
 
+/// A frame of the traversal stack, for the forward cursor `index` is
+/// the next key to yield and for the backward cursor it's one past it
 struct NextNodes<K: Ord, V> {
     node: OpNode<K, V>,
-    index: Cell<usize>,
+    index: usize,
 }
 
 pub struct Iter<'a, K: Ord, V> {
     next_nodes: Vec<NextNodes<K, V>>,
-    seen: HashSet<OpNode<K, V>>,
     next_back_nodes: Vec<NextNodes<K, V>>,
-    seen_back: HashSet<OpNode<K, V>>,
+    // the number of pairs not yielded from either end
+    remaining: usize,
     _marker: PhantomData<&'a Node<K, V>>,
 }
 
+/// The position of a key-value pair, which is the inner data
+/// of a node and the index in it
+type DataPos<K, V> = (NonNull<VecDeque<Data<K, V>>>, usize);
+
 impl<'a, K: Ord, V> Iter<'a, K, V> {
-    fn next_ascending(&mut self) -> Option<(&'a K, &'a V)> {
+    /// Push the node and all the first children below it
+    fn push_left_edge(&mut self, mut node: OpNode<K, V>) {
+        while node.is_some() {
+            self.next_nodes.push(NextNodes { node, index: 0 });
+            node = Node::get_child_by_index(node, 0);
+        }
+    }
+
+    /// Push the node and all the last children below it
+    fn push_right_edge(&mut self, mut node: OpNode<K, V>) {
+        while node.is_some() {
+            let data_size = Node::get_data_size(node);
+            self.next_back_nodes.push(NextNodes {
+                node,
+                index: data_size,
+            });
+            node = Node::get_child_by_index(node, data_size);
+        }
+    }
+
+    fn next_ascending(&mut self) -> Option<DataPos<K, V>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
         loop {
-            let head_node = self.next_nodes.pop();
-            let cur_node = if head_node.is_some() {
-                let h = head_node.unwrap();
-                if self.seen.contains(&h.node) {
-                    None
-                } else {
-                    Some(h)
-                }
-            } else {
-                None
-            };
+            let top = self.next_nodes.last_mut()?;
+            let node = top.node;
+            let cur_idx = top.index;
+            if cur_idx >= Node::get_data_size(node) {
+                self.next_nodes.pop();
+                continue;
+            }
+            top.index += 1;
+            self.push_left_edge(Node::get_child_by_index(node, cur_idx + 1));
+            break Node::get_inner_data(node).map(|d| (d, cur_idx));
+        }
+    }
 
-            match cur_node {
-                None => {
-                    if self.next_nodes.len() == 0 {
-                        break None;
-                    } else {
-                        continue;
-                    }
-                }
-                Some(node_wrapper) => unsafe {
-                    let cur_idx = node_wrapper.index.get();
-                    let node = node_wrapper.node;
-                    let data = Node::get_inner_data(node);
-                    let is_the_last_data = Node::get_data_size(node) - 1 == cur_idx;
-                    let left = Node::get_child_by_index(node, cur_idx);
-                    let right = Node::get_child_by_index(node, cur_idx + 1);
-                    let left_child = if self.seen.contains(&left) || left.is_none() {
-                        None
-                    } else {
-                        left
-                    };
-                    let right_child = if self.seen.contains(&right) || right.is_none() {
-                        None
-                    } else {
-                        right
-                    };
+    fn next_descending(&mut self) -> Option<DataPos<K, V>> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        loop {
+            let top = self.next_back_nodes.last_mut()?;
+            let node = top.node;
+            if top.index == 0 {
+                self.next_back_nodes.pop();
+                continue;
+            }
+            top.index -= 1;
+            let cur_idx = top.index;
+            self.push_right_edge(Node::get_child_by_index(node, cur_idx));
+            break Node::get_inner_data(node).map(|d| (d, cur_idx));
+        }
+    }
+
+    /// Turn a position into references of key and value
+    #[inline]
+    fn key_value(pos: DataPos<K, V>) -> (&'a K, &'a V) {
+        let (data, idx) = pos;
+        let d = unsafe { &(&*data.as_ptr())[idx] };
+        (&d.key, &d.value)
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for Iter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_ascending().map(Iter::key_value)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.remaining, Some(self.remaining))
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for Iter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.next_descending().map(Iter::key_value)
+    }
+}
+
+impl<'a, K: Ord, V> ExactSizeIterator for Iter<'a, K, V> {}
+
+impl<'a, K: Ord, V> FusedIterator for Iter<'a, K, V> {}
+
+pub struct IterMut<'a, K: Ord, V> {
+    inner: Iter<'a, K, V>,
+    _marker: PhantomData<&'a mut Node<K, V>>,
+}
+
+impl<'a, K: Ord, V> Iterator for IterMut<'a, K, V> {
+    type Item = (&'a K, &'a mut V);
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next_ascending().map(|(data, idx)| {
+            let d = unsafe { &mut (&mut *data.as_ptr())[idx] };
+            (&d.key, &mut d.value)
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, K: Ord, V> ExactSizeIterator for IterMut<'a, K, V> {}
+
+impl<'a, K: Ord, V> FusedIterator for IterMut<'a, K, V> {}
+
+/// An iterator over the key-value pairs inside a range of keys
+pub struct Range<'a, K: Ord, V> {
+    front: KeyPos<K, V>,
+    back: KeyPos<K, V>,
+    _marker: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K: Ord, V> Range<'a, K, V> {
+    /// Turn a position into references of key and value
+    #[inline]
+    fn key_value(node: OpNode<K, V>, idx: usize) -> Option<(&'a K, &'a V)> {
+        Node::get_inner_data(node).map(|data| {
+            let d = unsafe { &(&*data.as_ptr())[idx] };
+            (&d.key, &d.value)
+        })
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for Range<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        let (node, idx) = self.front?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.front = Node::next_position(node, idx);
+        }
+        Range::key_value(node, idx)
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for Range<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (node, idx) = self.back?;
+        if self.front == self.back {
+            self.front = None;
+            self.back = None;
+        } else {
+            self.back = Node::prev_position(node, idx);
+        }
+        Range::key_value(node, idx)
+    }
+}
+
+/// An iterator which can peek both ends without advancing
+pub struct PeekingIter<'a, K: Ord, V> {
+    iter: Iter<'a, K, V>,
+    front: Option<(&'a K, &'a V)>,
+    back: Option<(&'a K, &'a V)>,
+    remaining: usize,
+}
+
+impl<'a, K: Ord, V> PeekingIter<'a, K, V> {
+    /// Peeking the next key-value pair from the front
+    pub fn peek(&self) -> Option<(&'a K, &'a V)> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.front
+        }
+    }
+
+    /// Peeking the next key-value pair from the back
+    pub fn peek_back(&self) -> Option<(&'a K, &'a V)> {
+        if self.remaining == 0 {
+            None
+        } else {
+            self.back
+        }
+    }
+}
+
+impl<'a, K: Ord, V> Iterator for PeekingIter<'a, K, V> {
+    type Item = (&'a K, &'a V);
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let out = self.front;
+        // the last pair may already be held by the back
+        self.front = if self.remaining > 0 {
+            self.iter.next().or(self.back)
+        } else {
+            None
+        };
+        out
+    }
+}
+
+impl<'a, K: Ord, V> DoubleEndedIterator for PeekingIter<'a, K, V> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.remaining == 0 {
+            return None;
+        }
+        self.remaining -= 1;
+        let out = self.back;
+        self.back = if self.remaining > 0 {
+            self.iter.next_back().or(self.front)
+        } else {
+            None
+        };
+        out
+    }
+}
+
+/// Collecting always builds a B-tree of order 5,
+/// use `BTree::with_order_from_iter` for another order
+impl<K: Ord, V> FromIterator<(K, V)> for BTree<K, V> {
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        BTree::with_order_from_iter(5, iter)
+    }
+}
+
+/// The B-tree is built with the order of 5, use
+/// `BTree::with_order_from_iter` for other orders
+impl<K: Ord, V, const N: usize> From<[(K, V); N]> for BTree<K, V> {
+    fn from(arr: [(K, V); N]) -> Self {
+        BTree::with_order_from_iter(5, arr)
+    }
+}
+
+/// The B-tree is built with the order of 5 from the
+/// already sorted pairs of a `BTreeMap`
+impl<K: Ord, V> From<BTreeMap<K, V>> for BTree<K, V> {
+    fn from(map: BTreeMap<K, V>) -> Self {
+        BTree::from_sorted(5, map)
+    }
+}
+
+impl<K: Ord, V> From<BTree<K, V>> for BTreeMap<K, V> {
+    fn from(b: BTree<K, V>) -> Self {
+        b.into_iter().collect()
+    }
+}
+
+impl<K: Ord, V> Extend<(K, V)> for BTree<K, V> {
+    fn extend<T: IntoIterator<Item = (K, V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(k, v);
+        }
+    }
+}
+
+impl<'a, K: Ord + Copy, V: Copy> Extend<(&'a K, &'a V)> for BTree<K, V> {
+    fn extend<T: IntoIterator<Item = (&'a K, &'a V)>>(&mut self, iter: T) {
+        for (k, v) in iter {
+            self.insert(*k, *v);
+        }
+    }
+}
+
+impl<K, Q, V> Index<&Q> for BTree<K, V>
+where
+    K: Ord + Borrow<Q>,
+    Q: Ord + ?Sized,
+{
+    type Output = V;
+
+    /// Get the reference of value by key
+    ///
+    /// # Panics
+    ///
+    /// Panics if the key is not in the tree
+    fn index(&self, k: &Q) -> &V {
+        self.get(k).expect("key not found in the BTree")
+    }
+}
+
+impl<K: Ord, V> IntoIterator for BTree<K, V> {
+    type Item = (K, V);
+    type IntoIter = IntoIter<K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter(self)
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a BTree<K, V> {
+    type Item = (&'a K, &'a V);
+    type IntoIter = Iter<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, K: Ord, V> IntoIterator for &'a mut BTree<K, V> {
+    type Item = (&'a K, &'a mut V);
+    type IntoIter = IterMut<'a, K, V>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<K: Ord + Clone, V: Clone> Clone for BTree<K, V> {
+    fn clone(&self) -> Self {
+        let mut out = BTree::<K, V>::new(self.max_key_num);
+        for (k, v) in self.iter() {
+            out.insert(k.clone(), v.clone())
+        }
+        out
+    }
+}
+
+/// Formatting the keys of a node and it's children
+struct DebugNode<'a, K: Ord, V> {
+    node: OpNode<K, V>,
+    _marker: PhantomData<&'a Node<K, V>>,
+}
+
+impl<'a, K: Ord + fmt::Debug, V> fmt::Debug for DebugNode<'a, K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let keys: Vec<&K> = match Node::get_inner_data(self.node) {
+            None => Vec::new(),
+            Some(data) => unsafe { (*data.as_ptr()).iter().map(|d| &d.key).collect() },
+        };
+        let mut s = f.debug_struct("Node");
+        s.field("keys", &keys);
+        if let Some(children) = Node::get_children(self.node) {
+            let children: Vec<DebugNode<'a, K, V>> = unsafe {
+                (*children.as_ptr())
+                    .iter()
+                    .map(|c| DebugNode {
+                        node: *c,
+                        _marker: PhantomData,
+                    })
+                    .collect()
+            };
+            s.field("children", &children);
+        }
+        s.finish()
+    }
+}
+
+/// Two trees are equal if they hold the same key-value pairs,
+/// the orders and the shapes of the trees don't matter
+impl<K: Ord, V: PartialEq> PartialEq for BTree<K, V> {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl<K: Ord, V: Eq> Eq for BTree<K, V> {}
+
+/// Hashing the key-value pairs in ascending key order
+/// so equal trees always have the same hash
+impl<K: Ord + Hash, V: Hash> Hash for BTree<K, V> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_usize(self.len);
+        for (k, v) in self.iter() {
+            k.hash(state);
+            v.hash(state);
+        }
+    }
+}
+
+/// Serializing as a map in ascending key order,
+/// the order of the tree is not written
+#[cfg(feature = "serde")]
+impl<K: Ord + serde::Serialize, V: serde::Serialize> serde::Serialize for BTree<K, V> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.iter())
+    }
+}
+
+/// Deserializing from a map into a B-tree of order 5
+/// which is the same as collecting from an iterator
+#[cfg(feature = "serde")]
+impl<'de, K, V> serde::Deserialize<'de> for BTree<K, V>
+where
+    K: Ord + serde::Deserialize<'de>,
+    V: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct BTreeVisitor<K, V>(PhantomData<(K, V)>);
+
+        impl<'de, K, V> serde::de::Visitor<'de> for BTreeVisitor<K, V>
+        where
+            K: Ord + serde::Deserialize<'de>,
+            V: serde::Deserialize<'de>,
+        {
+            type Value = BTree<K, V>;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a map")
+            }
+
+            fn visit_map<M: serde::de::MapAccess<'de>>(
+                self,
+                mut map: M,
+            ) -> Result<Self::Value, M::Error> {
+                let mut out = BTree::new(5);
+                while let Some((k, v)) = map.next_entry()? {
+                    out.insert(k, v);
+                }
+                Ok(out)
+            }
+        }
+
+        deserializer.deserialize_map(BTreeVisitor(PhantomData))
+    }
+}
+
+/// `{:?}` formats the tree like a map in ascending key order
+/// and `{:#?}` shows the keys held by every node instead
+impl<K: Ord + fmt::Debug, V: fmt::Debug> fmt::Debug for BTree<K, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            let mut s = f.debug_struct("BTree");
+            s.field("order", &self.max_key_num).field("len", &self.len);
+            if self.root_node.is_some() {
+                s.field(
+                    "root",
+                    &DebugNode::<K, V> {
+                        node: self.root_node,
+                        _marker: PhantomData,
+                    },
+                );
+            }
+            s.finish()
+        } else {
+            f.debug_map().entries(self.iter()).finish()
+        }
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl<K: Ord, V> BTree<K, V> {
+    /// Encoding the tree into a flat sorted binary layout
+    /// which is the order and the number of entries followed by every key
+    /// and value in ascending order, each of them prefixed with it's length
+    ///
+    /// # Panics
+    ///
+    /// Panics if an encoded key or value is longer than `u32::MAX` bytes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<u32, u32> = BTree::new(4);
+    /// b.insert(1, 1);
+    /// let bytes = b.to_bytes(|k| k.to_le_bytes().to_vec(), |v| v.to_le_bytes().to_vec());
+    /// assert_eq!(bytes.len(), 8 + 8 + 2 * (4 + 4));
+    /// ```
+    pub fn to_bytes<FK, FV>(&self, encode_key: FK, encode_value: FV) -> Vec<u8>
+    where
+        FK: Fn(&K) -> Vec<u8>,
+        FV: Fn(&V) -> Vec<u8>,
+    {
+        let mut out = Vec::new();
+        bytes::write_u64(&mut out, self.max_key_num as u64);
+        bytes::write_u64(&mut out, self.len as u64);
+        for (k, v) in self.iter() {
+            bytes::write_chunk(&mut out, &encode_key(k));
+            bytes::write_chunk(&mut out, &encode_value(v));
+        }
+        out
+    }
+
+    /// Decoding a tree from the layout written by `to_bytes`
+    /// and the decoded tree has the same order
+    /// Returns None if the bytes are malformed, any decoder fails
+    /// or the keys are not strictly ascending
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// use std::convert::TryInto;
+    ///
+    /// let mut b: BTree<u32, u32> = BTree::new(4);
+    /// b.insert(1, 1);
+    /// let bytes = b.to_bytes(|k| k.to_le_bytes().to_vec(), |v| v.to_le_bytes().to_vec());
+    /// let decode = |b: &[u8]| b.try_into().ok().map(u32::from_le_bytes);
+    /// let b2 = BTree::from_bytes(&bytes, decode, decode).unwrap();
+    /// assert_eq!(b2.get(&1), Some(&1));
+    /// ```
+    pub fn from_bytes<FK, FV>(bytes: &[u8], decode_key: FK, decode_value: FV) -> Option<Self>
+    where
+        FK: Fn(&[u8]) -> Option<K>,
+        FV: Fn(&[u8]) -> Option<V>,
+    {
+        use core::convert::TryFrom;
+
+        let mut reader = bytes::Reader::new(bytes);
+        let order = usize::try_from(reader.read_u64()?).ok()?;
+        let len = reader.read_u64()?;
+        if order < 3 || order.checked_add(1).is_none() {
+            return None;
+        }
+        let pairs = reader.read_sorted_pairs(len, decode_key, decode_value)?;
+        Some(BTree::from_sorted(order, pairs))
+    }
+}
+
+unsafe impl<K: Ord + Send, V: Send> Send for BTree<K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for BTree<K, V> {}
+
+unsafe impl<K: Ord + Send, V: Send> Send for Iter<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for Iter<'_, K, V> {}
+
+unsafe impl<K: Ord + Send, V: Send> Send for IterMut<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for IterMut<'_, K, V> {}
+
+unsafe impl<K: Ord + Send, V: Send> Send for PeekingIter<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for PeekingIter<'_, K, V> {}
+
+unsafe impl<K: Ord + Send, V: Send> Send for Range<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for Range<'_, K, V> {}
+
+unsafe impl<K: Ord + Send, V: Send> Send for OccupiedEntry<'_, K, V> {}
+
+unsafe impl<K: Ord + Sync, V: Sync> Sync for OccupiedEntry<'_, K, V> {}
+
+impl<K: Ord, V> BTree<K, V> {
+    /// Create a B-tree with some order.
+    /// and the order is maximum number of keys that
+    /// a node in the B-tree can hold
+    /// and the minimum number of order is 3 which will make this a 2-3 tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let b: BTree<i32, i32> = BTree::new(4);
+    /// ```
+    pub fn new(order: usize) -> Self {
+        assert!(order >= 3, "Degree should be greater or equal to 3");
+        BTree {
+            root_node: None,
+            len: 0,
+            max_key_num: order,
+            min_key_num: (order + 1).div_ceil(2) - 1,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Create a B-tree with some order and adding
+    /// all the key-value pairs of an iterator into it,
+    /// the later value wins if a key appears more than once
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let b = BTree::with_order_from_iter(3, (0..10).map(|i| (i, i * 2)));
+    /// assert_eq!(b.len(), 10);
+    /// assert_eq!(b.get(&4), Some(&8));
+    /// ```
+    pub fn with_order_from_iter<I: IntoIterator<Item = (K, V)>>(order: usize, iter: I) -> Self {
+        let mut out = BTree::new(order);
+        for (k, v) in iter {
+            out.insert(k, v);
+        }
+        out
+    }
+
+    /// Adding a pair of key and value into the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// b.insert(1, 1);
+    /// ```   
+    pub fn insert(&mut self, k: K, v: V) {
+        self._add(k, v);
+    }
+
+    /// Poping out the minimum key-value pair in the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.pop_min(), Some((1, 1)))
+    /// ```   
+    pub fn pop_min(&mut self) -> Option<(K, V)> {
+        self._pop_min_data().map(|n| (n.key, n.value))
+    }
+
+    /// Poping out the maximum key-value pair in the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.pop_max(), Some((3, 3)));
+    /// ```   
+    pub fn pop_max(&mut self) -> Option<(K, V)> {
+        self._pop_max_data().map(|n| (n.key, n.value))
+    }
+
+    /// Poping out the first key-value pair which has the minimum key,
+    /// the same as `pop_min` and named after `BTreeMap::pop_first`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.pop_first(), Some((1, 1)));
+    /// ```
+    pub fn pop_first(&mut self) -> Option<(K, V)> {
+        self.pop_min()
+    }
+
+    /// Poping out the last key-value pair which has the maximum key,
+    /// the same as `pop_max` and named after `BTreeMap::pop_last`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.pop_last(), Some((3, 3)));
+    /// ```
+    pub fn pop_last(&mut self) -> Option<(K, V)> {
+        self.pop_max()
+    }
+
+    /// Peeking the minimum key-value pair in the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.first_key_value(), Some((&1, &1)));
+    /// ```
+    pub fn first_key_value(&self) -> Option<(&K, &V)> {
+        let min_node = Node::get_minimum_node(self.root_node);
+        Node::get_inner_data(min_node)
+            .and_then(|data| unsafe { (*data.as_ptr()).front() })
+            .map(|d| (&d.key, &d.value))
+    }
+
+    /// Peeking the maximum key-value pair in the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.last_key_value(), Some((&3, &3)));
+    /// ```
+    pub fn last_key_value(&self) -> Option<(&K, &V)> {
+        let max_node = Node::get_maximum_node(self.root_node);
+        Node::get_inner_data(max_node)
+            .and_then(|data| unsafe { (*data.as_ptr()).back() })
+            .map(|d| (&d.key, &d.value))
+    }
+
+    /// Get the entry of the minimum key for changing
+    /// or removing it in place
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// if let Some(mut e) = b.first_entry() {
+    ///     *e.get_mut() += 10;
+    /// }
+    /// assert_eq!(b.get(&1), Some(&11));
+    /// assert_eq!(b.first_entry().map(|e| e.remove_entry()), Some((1, 11)));
+    /// assert_eq!(b.len(), 2);
+    /// ```
+    pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, K, V>> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = Node::get_minimum_node(self.root_node);
+        Some(OccupiedEntry {
+            tree: self,
+            node,
+            idx: 0,
+        })
+    }
+
+    /// Get the entry of the maximum key for changing
+    /// or removing it in place
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// if let Some(mut e) = b.last_entry() {
+    ///     assert_eq!(e.insert(30), 3);
+    /// }
+    /// assert_eq!(b.last_key_value(), Some((&3, &30)));
+    /// assert_eq!(b.last_entry().map(|e| e.remove()), Some(30));
+    /// assert_eq!(b.len(), 2);
+    /// ```
+    pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, K, V>> {
+        if self.len == 0 {
+            return None;
+        }
+        let node = Node::get_maximum_node(self.root_node);
+        let idx = Node::get_data_size(node) - 1;
+        Some(OccupiedEntry {
+            tree: self,
+            node,
+            idx,
+        })
+    }
+
+    /// Give a reference of key try to return
+    /// the reference of value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.get(&2), Some(&2));
+    /// ```   
+    pub fn get<Q>(&self, k: &Q) -> Option<&V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self._get(k)
+    }
+
+    /// Give a reference of key try to return
+    /// the mutable reference of value
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// let v = b.get_mut(&2);
+    /// v.map(|i| *i += 10);
+    /// assert_eq!(b.get(&2), Some(&12));
+    /// ```   
+    pub fn get_mut<Q>(&mut self, k: &Q) -> Option<&mut V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self._get_mut(k)
+    }
+
+    /// Updating the key with a new value
+    /// and if the key is not exists it will
+    /// adding the key-value pair into the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(3);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// //b.set(2, 200);
+    /// ```   
+    pub fn set(&mut self, k: K, v: V) {
+        self.insert(k, v)
+    }
+
+    /// Check if Btree contains some key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert!(b.contains(&2));
+    ///
+    /// let mut s: BTree<String, i32> = BTree::new(4);
+    /// s.insert("abc".to_string(), 1);
+    /// assert!(s.contains("abc"));
+    /// ```   
+    pub fn contains<Q>(&self, k: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self._get(k).is_some()
+    }
+
+    /// Removing by key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.remove(&2), Some(2));
+    /// ```   
+    pub fn remove<Q>(&mut self, k: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self._remove(k).map(|d| d.value)
+    }
+
+    /// Making an iter of Btree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// let mut iter = b.iter();
+    /// assert_eq!(iter.next(), Some((&1, &1)));
+    /// assert_eq!(iter.next_back(), Some((&3, &3)));
+    /// ```      
+    pub fn iter<'a>(&'a self) -> Iter<'a, K, V> {
+        let mut iter = Iter {
+            next_nodes: Vec::new(),
+            next_back_nodes: Vec::new(),
+            remaining: self.len,
+            _marker: PhantomData,
+        };
+        if self.len > 0 {
+            iter.push_left_edge(self.root_node);
+            iter.push_right_edge(self.root_node);
+        }
+        iter
+    }
+
+    /// Making an iter of the keys in ascending order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 10), (2, 20), (3, 30)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.keys().collect::<Vec<_>>(), vec![&1, &2, &3]);
+    /// ```
+    pub fn keys(&self) -> impl DoubleEndedIterator<Item = &K> {
+        self.iter().map(|(k, _)| k)
+    }
+
+    /// Making an iter of the values in ascending order of keys
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 10), (2, 20), (3, 30)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.values().collect::<Vec<_>>(), vec![&10, &20, &30]);
+    /// ```
+    pub fn values(&self) -> impl DoubleEndedIterator<Item = &V> {
+        self.iter().map(|(_, v)| v)
+    }
+
+    /// Making a mutable iter of Btree, only the values are mutable
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// for (_, v) in b.iter_mut() {
+    ///     *v += 10;
+    /// }
+    /// assert_eq!(b.get(&1), Some(&11));
+    /// ```
+    pub fn iter_mut<'a>(&'a mut self) -> IterMut<'a, K, V> {
+        IterMut {
+            inner: self.iter(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Provides a forward iterator of mutable references of values
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, Vec<i32>> = BTree::new(4);
+    /// b.insert(1, vec![]);
+    /// b.insert(2, vec![]);
+    /// for v in b.values_mut() {
+    ///     v.push(7);
+    /// }
+    /// assert_eq!(b.get(&2), Some(&vec![7]));
+    /// ```
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.iter_mut().map(|(_, v)| v)
+    }
+
+    /// Get the length
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.len(), 3);
+    /// ```      
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// To tell if this tree is empty
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert!(!b.is_empty());
+    /// ```      
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Get the order of the tree which is the
+    /// maximum number of keys that a node can hold
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let b: BTree<i32, i32> = BTree::new(4);
+    /// assert_eq!(b.order(), 4);
+    /// ```
+    pub fn order(&self) -> usize {
+        self.max_key_num
+    }
+
+    /// Get the minimum number of keys that a node
+    /// other than the root should hold, which is
+    /// [(order + 1) / 2] - 1 where [] is the ceil operation
+    /// Removing keys rebalances a node which goes below it,
+    /// but for an even order splitting a full node leaves
+    /// one key less in the right half, so the real lower bound
+    /// of the nodes is [order / 2] - 1
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let b: BTree<i32, i32> = BTree::new(5);
+    /// assert_eq!(b.min_keys(), 2);
+    /// let b: BTree<i32, i32> = BTree::new(4);
+    /// assert_eq!(b.min_keys(), 2);
+    /// ```
+    pub fn min_keys(&self) -> usize {
+        self.min_key_num
+    }
+
+    /// Get the maximum number of keys that a node can hold
+    /// which is the same as the order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let b: BTree<i32, i32> = BTree::new(5);
+    /// assert_eq!(b.max_keys(), 5);
+    /// ```
+    pub fn max_keys(&self) -> usize {
+        self.max_key_num
+    }
+
+    /// Rebuilding the tree with a new order,
+    /// all the key-value pairs are kept and the nodes are
+    /// packed again in O(n) time
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = (0..100).map(|i| (i, i)).collect();
+    /// assert_eq!(b.order(), 5);
+    /// b.change_order(16);
+    /// assert_eq!(b.order(), 16);
+    /// assert_eq!(b.len(), 100);
+    /// assert_eq!(b.validate(), Ok(()));
+    /// ```
+    pub fn change_order(&mut self, new_order: usize) {
+        let old = mem::replace(self, BTree::new(new_order));
+        *self = BTree::from_sorted(new_order, old.into_sorted_vec());
+    }
+
+    /// Clearing the tree
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// b.clear();
+    /// assert_eq!(b.len(), 0);
+    /// ```      
+    pub fn clear(&mut self) {
+        *self = Self::new(self.max_key_num);
+    }
 
-                    if left_child.is_none() && right_child.is_none() && !is_the_last_data {
-                        node_wrapper.index.set(cur_idx + 1);
-                        self.next_nodes.push(node_wrapper);
-                        break data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                    } else if left_child.is_none() && right_child.is_none() && is_the_last_data {
-                        self.seen.insert(node);
-                        break data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                    } else if left_child.is_some() && right_child.is_none() && !is_the_last_data {
-                        self.next_nodes.push(node_wrapper);
-                        self.next_nodes.push(NextNodes {
-                            node: left_child,
-                            index: Cell::new(0),
-                        });
-                        continue;
-                    } else if left_child.is_some() && right_child.is_none() && is_the_last_data {
-                        self.next_nodes.push(node_wrapper);
-                        self.next_nodes.push(NextNodes {
-                            node: left_child,
-                            index: Cell::new(0),
-                        });
-                        continue;
-                    } else if left_child.is_some() && right.is_some() && !is_the_last_data {
-                        self.next_nodes.push(node_wrapper);
-                        self.next_nodes.push(NextNodes {
-                            node: left_child,
-                            index: Cell::new(0),
-                        });
-                        continue;
-                    } else if left_child.is_some() && right_child.is_some() && is_the_last_data {
-                        self.next_nodes.push(node_wrapper);
-                        self.next_nodes.push(NextNodes {
-                            node: left_child,
-                            index: Cell::new(0),
-                        });
-                        continue;
-                    } else if left_child.is_none() && right_child.is_some() && !is_the_last_data {
-                        let out = data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                        node_wrapper.index.set(cur_idx + 1);
-                        self.next_nodes.push(node_wrapper);
-                        break out;
-                    } else {
-                        // left_child.is_none && right_child.is_some() && is_the_last_data
-                        self.seen.insert(node);
-                        let out = data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                        node_wrapper.index.set(cur_idx);
-                        self.next_nodes.push(NextNodes {
-                            node: right_child,
-                            index: Cell::new(0),
-                        });
-                        break out;
+    /// Removing all the keys of an ascending sorted slice
+    /// and returns the number of keys actually removed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.remove_sorted(&[1, 3, 5]), 2);
+    /// assert_eq!(b.len(), 1);
+    /// ```
+    pub fn remove_sorted(&mut self, keys: &[K]) -> usize {
+        debug_assert!(
+            keys.windows(2).all(|w| w[0] <= w[1]),
+            "the keys should be sorted in ascending order"
+        );
+        // merge-walk the sorted keys against the entries of the tree
+        // so only the keys that exist are going to be removed
+        let mut found: Vec<usize> = Vec::new();
+        let mut iter = self.iter().peekable();
+        let mut idx: usize = 0;
+        while idx < keys.len() {
+            match iter.peek() {
+                None => {
+                    break;
+                }
+                Some((k, _)) => match (*k).cmp(&keys[idx]) {
+                    Ordering::Less => {
+                        iter.next();
+                    }
+                    Ordering::Equal => {
+                        found.push(idx);
+                        iter.next();
+                        idx += 1;
+                    }
+                    Ordering::Greater => {
+                        idx += 1;
                     }
                 },
             }
         }
+        for idx in found.iter() {
+            self._remove(&keys[*idx]);
+        }
+        found.len()
     }
 
-    fn next_descending(&mut self) -> Option<(&'a K, &'a V)> {
-        loop {
-            let last_node = self.next_back_nodes.pop();
-            let cur_node = if last_node.is_some() {
-                let l = last_node.unwrap();
-                if self.seen_back.contains(&l.node) {
-                    None
-                } else {
-                    Some(l)
-                }
-            } else {
-                None
+    /// Cloning the entries within a range into a new tree
+    /// with the same order, and this tree will not be changed
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// let c = b.clone_range(2..);
+    /// assert_eq!(c.len(), 2);
+    /// assert_eq!(b.len(), 3);
+    /// ```
+    pub fn clone_range<R: RangeBounds<K>>(&self, range: R) -> Self
+    where
+        K: Clone,
+        V: Clone,
+    {
+        BTree::from_sorted(
+            self.max_key_num,
+            self.range(range).map(|(k, v)| (k.clone(), v.clone())),
+        )
+    }
+
+    /// Adding a pair of key and value into the tree and returns
+    /// the number of nodes visited or modified, including the splits
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(3);
+    /// assert_eq!(b.insert_counting(1, 1), 1);
+    /// ```
+    pub fn insert_counting(&mut self, k: K, v: V) -> usize {
+        self._add(k, v)
+    }
+
+    /// Grouping the keys by their values, it yields each distinct value
+    /// in ascending order together with the sorted keys mapping to it
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 1)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// let groups: Vec<_> = b.group_by_value().collect();
+    /// assert_eq!(groups, vec![(&1, vec![&1, &3]), (&2, vec![&2])]);
+    /// ```
+    pub fn group_by_value(&self) -> impl Iterator<Item = (&V, Vec<&K>)>
+    where
+        V: Ord,
+    {
+        query::group_by_value(self.iter()).into_iter()
+    }
+
+    /// Get the entry which is `delta` positions away from a key
+    /// in sorted order, negative `delta` goes toward smaller keys
+    /// Returns None if the key is absent or the offset is out of range
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.offset_from(&1, 2), Some((&3, &3)));
+    /// assert_eq!(b.offset_from(&3, -1), Some((&2, &2)));
+    /// assert_eq!(b.offset_from(&3, 1), None);
+    /// ```
+    pub fn offset_from(&self, k: &K, delta: isize) -> Option<(&K, &V)> {
+        let pos = match self._count_less(k) {
+            (less, true) => less,
+            _ => return None,
+        };
+        self.select(pos.checked_add_signed(delta)?)
+    }
+
+    /// Get the fill factor, which is the average ratio of the keys
+    /// held by the nodes to the maximum number of keys of a node
+    /// An empty tree has a fill factor of 0
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.fill_factor(), 0.75);
+    /// ```
+    pub fn fill_factor(&self) -> f64 {
+        let node_count = self._node_count();
+        if node_count == 0 {
+            0.0
+        } else {
+            self.len as f64 / (node_count * self.max_key_num) as f64
+        }
+    }
+
+    /// Get the number of levels in the tree
+    /// An empty tree has a height of 0
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.height(), 1);
+    /// ```
+    pub fn height(&self) -> usize {
+        self._height()
+    }
+
+    /// Get the balance score, which is the ratio of the minimum
+    /// possible height for the current length and order
+    /// to the actual height, 1.0 means the tree is as low as possible
+    /// An empty tree has a balance score of 1.0
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.balance_score(), 1.0);
+    /// ```
+    pub fn balance_score(&self) -> f64 {
+        let height = self._height();
+        if height == 0 {
+            return 1.0;
+        }
+        // a full tree of h levels holds (order + 1)^h - 1 keys
+        let mut min_height: usize = 1;
+        let mut capacity = self.max_key_num;
+        while capacity < self.len {
+            min_height += 1;
+            capacity = capacity * (self.max_key_num + 1) + self.max_key_num;
+        }
+        min_height as f64 / height as f64
+    }
+
+    /// Iterating the entries in the order defined by a value comparator
+    /// Note all the entries are buffered and sorted before yielding
+    /// and entries with equal values stay in key order
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 20), (2, 30), (3, 10)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// let keys: Vec<_> = b.iter_by_value(|a, b| a.cmp(b)).map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, vec![3, 1, 2]);
+    /// ```
+    pub fn iter_by_value<F>(&self, mut cmp: F) -> impl Iterator<Item = (&K, &V)>
+    where
+        F: FnMut(&V, &V) -> Ordering,
+    {
+        let mut entries: Vec<(&K, &V)> = self.iter().collect();
+        entries.sort_by(|a, b| cmp(a.1, b.1));
+        entries.into_iter()
+    }
+
+    /// Provides an iterator which can peek both ends without advancing
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// let mut iter = b.peekable_iter();
+    /// assert_eq!(iter.peek(), Some((&1, &1)));
+    /// assert_eq!(iter.peek_back(), Some((&3, &3)));
+    /// assert_eq!(iter.next(), Some((&1, &1)));
+    /// ```
+    pub fn peekable_iter(&self) -> PeekingIter<'_, K, V> {
+        let mut iter = self.iter();
+        let front = iter.next();
+        let back = iter.next_back().or(front);
+        PeekingIter {
+            iter,
+            front,
+            back,
+            remaining: self.len,
+        }
+    }
+
+    /// Get the length of the longest run of values in key order
+    /// where each value is strictly greater than the previous one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 3), (2, 1), (3, 2)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.longest_increasing_value_run(), 2);
+    /// ```
+    pub fn longest_increasing_value_run(&self) -> usize
+    where
+        V: PartialOrd,
+    {
+        let mut longest: usize = 0;
+        let mut cur_run: usize = 0;
+        let mut prev: Option<&V> = None;
+        for (_, v) in self.iter() {
+            cur_run = match prev {
+                Some(p) if v > p => cur_run + 1,
+                _ => 1,
             };
+            if cur_run > longest {
+                longest = cur_run;
+            }
+            prev = Some(v);
+        }
+        longest
+    }
 
-            match cur_node {
+    /// Removing up to n smallest key-value pairs
+    /// and returns them in ascending order
+    /// If n is not less than the length the tree will be drained
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// assert_eq!(b.pop_min_n(2), vec![(1, 1), (2, 2)]);
+    /// assert_eq!(b.len(), 1);
+    /// ```
+    pub fn pop_min_n(&mut self, n: usize) -> Vec<(K, V)> {
+        let mut out = Vec::with_capacity(n.min(self.len));
+        while out.len() < n {
+            match self._pop_min_data() {
                 None => {
-                    if self.next_back_nodes.len() == 0 {
-                        break None;
-                    } else {
-                        continue;
-                    }
+                    break;
                 }
-                Some(node_wrapper) => unsafe {
-                    let cur_idx = node_wrapper.index.get();
-                    let node = node_wrapper.node;
-                    let data = Node::get_inner_data(node);
-                    let is_the_first_data = cur_idx == 0;
-                    let left = Node::get_child_by_index(node, cur_idx);
-                    let right = Node::get_child_by_index(node, cur_idx + 1);
-                    let left_child = if self.seen_back.contains(&left) || left.is_none() {
-                        None
-                    } else {
-                        left
-                    };
-                    let right_child = if self.seen_back.contains(&right) || right.is_none() {
-                        None
-                    } else {
-                        right
-                    };
+                Some(d) => out.push((d.key, d.value)),
+            }
+        }
+        out
+    }
 
-                    if left_child.is_none() && right_child.is_none() && !is_the_first_data {
-                        node_wrapper.index.set(cur_idx - 1);
-                        self.next_back_nodes.push(node_wrapper);
-                        break data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                    } else if left_child.is_none() && right_child.is_none() && is_the_first_data {
-                        self.seen_back.insert(node);
-                        break data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                    } else if left_child.is_some() && right_child.is_none() && !is_the_first_data {
-                        let out = data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                        node_wrapper.index.set(cur_idx - 1);
-                        self.next_back_nodes.push(node_wrapper);
-                        break out;
-                    } else if left_child.is_some() && right_child.is_none() && is_the_first_data {
-                        self.seen_back.insert(node);
-                        let out = data
-                            .as_ref()
-                            .map(|d| &(*d.as_ptr())[cur_idx])
-                            .map(|d| (&d.key, &d.value));
-                        node_wrapper.index.set(cur_idx);
-                        self.next_back_nodes.push(NextNodes {
-                            node: left_child,
-                            index: Cell::new(Node::get_data_size(left_child) - 1),
-                        });
-                        break out;
-                    } else if left_child.is_some() && right_child.is_some() && !is_the_first_data {
-                        self.next_back_nodes.push(node_wrapper);
-                        self.next_back_nodes.push(NextNodes {
-                            node: right_child,
-                            index: Cell::new(Node::get_data_size(right_child) - 1),
-                        });
-                        continue;
-                    } else if left_child.is_some() && right_child.is_some() && is_the_first_data {
-                        self.next_back_nodes.push(node_wrapper);
-                        self.next_back_nodes.push(NextNodes {
-                            node: right_child,
-                            index: Cell::new(Node::get_data_size(right_child) - 1),
-                        });
-                        continue;
-                    } else if left_child.is_none() && right_child.is_some() && !is_the_first_data {
-                        self.next_back_nodes.push(node_wrapper);
-                        self.next_back_nodes.push(NextNodes {
-                            node: right_child,
-                            index: Cell::new(Node::get_data_size(right_child) - 1),
-                        });
-                        continue;
-                    } else {
-                        // left_child.is_none && right_child.is_some() && is_first_data
-                        self.next_back_nodes.push(node_wrapper);
-                        self.next_back_nodes.push(NextNodes {
-                            node: right_child,
-                            index: Cell::new(Node::get_data_size(right_child) - 1),
-                        });
-                        continue;
-                    }
-                },
+    /// Appending a run of key-value pairs sorted in ascending order
+    /// While the keys are greater than the maximum key of the tree they
+    /// are pushed into the rightmost leaf directly and the right spine
+    /// is only split when the leaf reaches the maximum number of keys
+    /// otherwise it falls back to inserting them one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// b.append_sorted_run((4..10).map(|i| (i, i)));
+    /// assert_eq!(b.len(), 9);
+    /// assert_eq!(b.get(&7), Some(&7));
+    /// ```
+    pub fn append_sorted_run<I: IntoIterator<Item = (K, V)>>(&mut self, run: I) {
+        // the sizes on the right spine are left behind while pushing
+        // and they are updated by the splits or when the run ends
+        let mut max_leaf = Node::get_maximum_node(self.root_node);
+        for (k, v) in run {
+            let is_greater = Node::get_inner_data(max_leaf)
+                .and_then(|d| unsafe { (*d.as_ptr()).back().map(|last| last.key < k) });
+            if is_greater == Some(true) {
+                self.len += 1;
+                Node::push_back_inner_data(max_leaf, Some(Data { key: k, value: v }));
+                let last_idx = Node::get_data_size(max_leaf) - 1;
+                if last_idx + 1 >= self.max_key_num {
+                    // the last key always ends up in the new rightmost leaf
+                    let (_, tracked) =
+                        self._up_merging_tracked(max_leaf, Some((max_leaf, last_idx)));
+                    max_leaf = tracked.and_then(|(n, _)| n);
+                }
+            } else {
+                self._update_sizes_up(max_leaf);
+                self._add(k, v);
+                max_leaf = Node::get_maximum_node(self.root_node);
             }
         }
+        self._update_sizes_up(max_leaf);
     }
-}
 
-impl<'a, K: Ord, V> Iterator for Iter<'a, K, V> {
-    type Item = (&'a K, &'a V);
-    fn next(&mut self) -> Option<Self::Item> {
-        self.next_ascending()
+    /// Checking all the B-tree rules and reporting
+    /// the first one that is violated
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// for i in 0..100 {
+    ///     b.insert(i, i)
+    /// }
+    /// assert_eq!(b.validate(), Ok(()));
+    /// ```
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        self._validate()
     }
-}
 
-impl<'a, K: Ord, V> DoubleEndedIterator for Iter<'a, K, V> {
-    fn next_back(&mut self) -> Option<Self::Item> {
-        self.next_descending()
+    /// Provides an iterator of the key-value pairs in ascending order
+    /// together with the previous and the next key
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// let mut it = b.iter_with_neighbors();
+    /// assert_eq!(it.next(), Some((None, (&1, &1), Some(&2))));
+    /// assert_eq!(it.next(), Some((Some(&1), (&2, &2), Some(&3))));
+    /// assert_eq!(it.next(), Some((Some(&2), (&3, &3), None)));
+    /// assert_eq!(it.next(), None);
+    /// ```
+    pub fn iter_with_neighbors(&self) -> impl Iterator<Item = (Option<&K>, (&K, &V), Option<&K>)> {
+        let mut iter = self.iter().peekable();
+        let mut prev: Option<&K> = None;
+        core::iter::from_fn(move || {
+            let (k, v) = iter.next()?;
+            let next = iter.peek().map(|(nk, _)| *nk);
+            let out = (prev, (k, v), next);
+            prev = Some(k);
+            Some(out)
+        })
     }
-}
 
-impl<K: Ord, V> FromIterator<(K, V)> for BTree<K, V> {
-    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
-        let inputs: Vec<_> = iter.into_iter().collect();
-        if inputs.is_empty() {
-            return BTree::<K, V>::new(5);
-        }
-        let mut out = BTree::<K, V>::new(5);
-        for (k, v) in inputs {
-            out.insert(k, v);
+    /// Provides a double-ended iterator of the key-value pairs
+    /// whose keys are inside the range in ascending order
+    /// An empty iterator is returned when the start is greater than the end
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// for i in 0..30 {
+    ///     b.insert(i, i * 10)
+    /// }
+    /// let keys: Vec<_> = b.range(10..13).map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, vec![10, 11, 12]);
+    /// let keys: Vec<_> = b.range(27..).rev().map(|(k, _)| *k).collect();
+    /// assert_eq!(keys, vec![29, 28, 27]);
+    /// ```
+    pub fn range<R: RangeBounds<K>>(&self, range: R) -> Range<'_, K, V> {
+        let front = self._lower_bound_pos(range.start_bound());
+        let back = self._upper_bound_pos(range.end_bound());
+        let is_empty = match (front, back) {
+            (Some((f_node, f_idx)), Some((b_node, b_idx))) => {
+                let first: Option<(&K, &V)> = Range::key_value(f_node, f_idx);
+                let last: Option<(&K, &V)> = Range::key_value(b_node, b_idx);
+                first.map(|(k, _)| k) > last.map(|(k, _)| k)
+            }
+            _ => true,
+        };
+        if is_empty {
+            Range {
+                front: None,
+                back: None,
+                _marker: PhantomData,
+            }
+        } else {
+            Range {
+                front,
+                back,
+                _marker: PhantomData,
+            }
         }
-        out
-    }
-}
-
-impl<K: Ord, V> IntoIterator for BTree<K, V> {
-    type Item = (K, V);
-    type IntoIter = IntoIter<K, V>;
-
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIter(self)
     }
-}
 
-impl<K: Ord + Copy, V: Copy> Clone for BTree<K, V> {
-    fn clone(&self) -> Self {
-        let mut out = BTree::<K, V>::new(self.max_key_num);
-        for (k, v) in self.iter() {
-            out.insert(*k, *v)
+    /// Keeping only the key-value pairs for which the predicate returns true
+    /// Note the tree is rebuilt from the kept pairs if any pair is dropped
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// b.retain(|k, _| k % 2 == 1);
+    /// assert_eq!(b.len(), 2);
+    /// ```
+    pub fn retain<F: FnMut(&K, &mut V) -> bool>(&mut self, mut f: F) {
+        let keeps: Vec<bool> = self.iter_mut().map(|(k, v)| f(k, v)).collect();
+        if keeps.iter().all(|keep| *keep) {
+            return;
         }
-        out
+        // removing moves the pairs between nodes so the kept pairs
+        // are appended into a new tree instead
+        let old = mem::replace(self, BTree::new(self.max_key_num));
+        self.append_sorted_run(
+            old.into_iter()
+                .zip(keeps)
+                .filter(|(_, keep)| *keep)
+                .map(|(pair, _)| pair),
+        );
     }
-}
 
-unsafe impl<K: Ord + Send, V: Send> Send for BTree<K, V> {}
-
-unsafe impl<K: Ord + Sync, V: Sync> Sync for BTree<K, V> {}
-
-unsafe impl<K: Ord + Send, V: Send> Send for Iter<'_, K, V> {}
-
-unsafe impl<K: Ord + Sync, V: Sync> Sync for Iter<'_, K, V> {}
-
-impl<K: Ord, V> BTree<K, V> {
-    /// Create a B-tree with some order.
-    /// and the order is maximum number of keys that
-    /// a node in the B-tree can hold
-    /// and the minimum number of order is 3 which will make this a 2-3 tree
+    /// Removing all the key-value pairs whose keys are in the range
+    /// Each pair is removed and rebalanced one by one starting from
+    /// the lower bound, which takes O(m * log n) time for m removed pairs
     ///
     /// # Example
     ///
     /// ```
     /// use ABtree::BTree;
-    /// let b: BTree<i32, i32> = BTree::new(4);
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// for i in 0..100 {
+    ///     b.insert(i, i)
+    /// }
+    /// b.remove_range(10..90);
+    /// assert_eq!(b.len(), 20);
+    /// assert_eq!(b.get(&50), None);
+    /// assert_eq!(b.validate(), Ok(()));
     /// ```
-    pub fn new(order: usize) -> Self {
-        assert!(order >= 3, "Degree should be greater or equal to 3");
-        let max_c = order + 1;
-        let min = max_c as f64 / 2.0_f64;
-        BTree {
-            root_node: None,
-            len: 0,
-            max_key_num: order,
-            min_key_num: min.ceil() as usize - 1,
-            _marker: PhantomData,
+    pub fn remove_range<R: RangeBounds<K>>(&mut self, range: R) {
+        while let pos @ Some((node, idx)) = self._lower_bound_pos(range.start_bound()) {
+            if !self
+                ._key_value_at(pos)
+                .is_some_and(|(k, _)| range.contains(k))
+            {
+                break;
+            }
+            self._remove_at(node, idx);
         }
     }
 
-    /// Adding a pair of key and value into the tree
+    /// Moving all the key-value pairs from other into self
+    /// and other becomes empty
+    /// The values from other win when the keys are the same
+    /// Note the pairs are inserted one by one, so the orders
+    /// of the two trees do not have to be the same
     ///
     /// # Example
     ///
     /// ```
     /// use ABtree::BTree;
     /// let mut b: BTree<i32, i32> = BTree::new(4);
-    /// b.insert(1, 1);
-    /// ```   
-    pub fn insert(&mut self, k: K, v: V) {
-        self._add(k, v)
+    /// let mut other: BTree<i32, i32> = BTree::new(5);
+    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// for (k, v) in data {
+    ///     b.insert(k, v)
+    /// }
+    /// other.insert(3, 30);
+    /// other.insert(4, 40);
+    /// b.append(&mut other);
+    /// assert_eq!(b.len(), 4);
+    /// assert_eq!(b.get(&3), Some(&30));
+    /// assert!(other.is_empty());
+    /// ```
+    pub fn append(&mut self, other: &mut BTree<K, V>) {
+        while let Some(d) = other._pop_min_data() {
+            self.insert(d.key, d.value);
+        }
     }
 
-    /// Poping out the minimum key-value pair in the tree
+    /// Splitting the tree at a key, the key-value pairs whose keys
+    /// are greater or equal to the key are moved into a new tree
+    /// with the same order
     ///
     /// # Example
     ///
@@ -1293,13 +3330,21 @@ impl<K: Ord, V> BTree<K, V> {
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// assert_eq!(b.pop_min(), Some((1, 1)))
-    /// ```   
-    pub fn pop_min(&mut self) -> Option<(K, V)> {
-        self._pop_min_data().map(|n| (n.key, n.value))
+    /// let upper = b.split_off(&2);
+    /// assert_eq!(b.len(), 1);
+    /// assert_eq!(upper.len(), 2);
+    /// ```
+    pub fn split_off(&mut self, k: &K) -> BTree<K, V> {
+        let mut out = BTree::new(self.max_key_num);
+        while self.last_key_value().is_some_and(|(max_k, _)| max_k >= k) {
+            if let Some(d) = self._pop_max_data() {
+                out.insert(d.key, d.value);
+            }
+        }
+        out
     }
 
-    /// Poping out the maximum key-value pair in the tree
+    /// Get the key-value pair with the largest key less than the given key
     ///
     /// # Example
     ///
@@ -1310,14 +3355,14 @@ impl<K: Ord, V> BTree<K, V> {
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// assert_eq!(b.pop_max(), Some((3, 3)));
-    /// ```   
-    pub fn pop_max(&mut self) -> Option<(K, V)> {
-        self._pop_max_data().map(|n| (n.key, n.value))
+    /// assert_eq!(b.predecessor(&2), Some((&1, &1)));
+    /// assert_eq!(b.predecessor(&1), None);
+    /// ```
+    pub fn predecessor(&self, k: &K) -> Option<(&K, &V)> {
+        self._key_value_at(self._upper_bound_pos(Bound::Excluded(k)))
     }
 
-    /// Give a reference of key try to return
-    /// the reference of value
+    /// Get the key-value pair with the smallest key greater than the given key
     ///
     /// # Example
     ///
@@ -1328,57 +3373,54 @@ impl<K: Ord, V> BTree<K, V> {
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// assert_eq!(b.get(&2), Some(&2));
-    /// ```   
-    pub fn get(&self, k: &K) -> Option<&V> {
-        let mut outs: Vec<_> = self.iter().filter(|n| n.0.eq(k)).collect();
-        if outs.len() == 0 {
-            None
-        } else {
-            outs.pop().map(|o| o.1)
-        }
+    /// assert_eq!(b.successor(&2), Some((&3, &3)));
+    /// assert_eq!(b.successor(&3), None);
+    /// ```
+    pub fn successor(&self, k: &K) -> Option<(&K, &V)> {
+        self._key_value_at(self._lower_bound_pos(Bound::Excluded(k)))
     }
 
-    /// Give a reference of key try to return
-    /// the mutable reference of value
+    /// Get the key-value pair with the largest key
+    /// less than or equal to the given key
     ///
     /// # Example
     ///
     /// ```
     /// use ABtree::BTree;
     /// let mut b: BTree<i32, i32> = BTree::new(4);
-    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// let data = [(1, 1), (3, 3), (5, 5)];
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// let v = b.get_mut(&2);
-    /// v.map(|i| *i += 10);
-    /// assert_eq!(b.get(&2), Some(&12));
-    /// ```   
-    pub fn get_mut(&mut self, k: &K) -> Option<&mut V> {
-        self._get_mut(k)
+    /// assert_eq!(b.floor(&3), Some((&3, &3)));
+    /// assert_eq!(b.floor(&4), Some((&3, &3)));
+    /// assert_eq!(b.floor(&0), None);
+    /// ```
+    pub fn floor(&self, k: &K) -> Option<(&K, &V)> {
+        self._key_value_at(self._upper_bound_pos(Bound::Included(k)))
     }
 
-    /// Updating the key with a new value
-    /// and if the key is not exists it will
-    /// adding the key-value pair into the tree
+    /// Get the key-value pair with the smallest key
+    /// greater than or equal to the given key
     ///
     /// # Example
     ///
     /// ```
     /// use ABtree::BTree;
-    /// let mut b: BTree<i32, i32> = BTree::new(3);
-    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// let data = [(1, 1), (3, 3), (5, 5)];
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// //b.set(2, 200);
-    /// ```   
-    pub fn set(&mut self, k: K, v: V) {
-        self.insert(k, v)
+    /// assert_eq!(b.ceiling(&3), Some((&3, &3)));
+    /// assert_eq!(b.ceiling(&4), Some((&5, &5)));
+    /// assert_eq!(b.ceiling(&6), None);
+    /// ```
+    pub fn ceiling(&self, k: &K) -> Option<(&K, &V)> {
+        self._key_value_at(self._lower_bound_pos(Bound::Included(k)))
     }
 
-    /// Check if Btree contains some key
+    /// Get the n-th smallest key-value pair, counting from 0
     ///
     /// # Example
     ///
@@ -1389,34 +3431,54 @@ impl<K: Ord, V> BTree<K, V> {
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// assert!(b.contains(&2));
-    /// ```   
-    pub fn contains(&self, k: &K) -> bool {
-        if self.is_empty() {
-            false
-        } else {
-            self.iter().any(|n| n.0.eq(k))
-        }
+    /// assert_eq!(b.select(b.len() / 2), Some((&2, &2)));
+    /// assert_eq!(b.select(3), None);
+    /// ```
+    pub fn select(&self, n: usize) -> Option<(&K, &V)> {
+        self._key_value_at(self._select_pos(n))
     }
 
-    /// Removing by key
+    /// Get the key-value pair at the given position in
+    /// sorted order, counting from 0
+    /// This is the same as `select` and takes O(log n) time
+    /// by the subtree sizes kept in each node
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    ///
+    /// let b: BTree<u32, u32> = (0..100).map(|i| (i * 10, i)).collect();
+    /// let page: Vec<_> = (20..25).filter_map(|i| b.nth(i)).collect();
+    /// assert_eq!(page[0], (&200, &20));
+    /// assert_eq!(b.nth(100), None);
+    /// ```
+    pub fn nth(&self, index: usize) -> Option<(&K, &V)> {
+        self.select(index)
+    }
+
+    /// Get the number of keys less than the given key
+    /// which does not have to be in the tree,
+    /// so it always returns Some
     ///
     /// # Example
     ///
     /// ```
     /// use ABtree::BTree;
     /// let mut b: BTree<i32, i32> = BTree::new(4);
-    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// let data = [(10, 1), (20, 2), (30, 3)];
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// assert_eq!(b.remove(&2), Some(2));
-    /// ```   
-    pub fn remove(&mut self, k: &K) -> Option<V> {
-        self._remove(k)
+    /// assert_eq!(b.rank(&30), Some(2));
+    /// assert_eq!(b.rank(&25), Some(2));
+    /// assert_eq!(b.rank(&40), Some(3));
+    /// ```
+    pub fn rank(&self, k: &K) -> Option<usize> {
+        Some(self._count_less(k).0)
     }
 
-    /// Making an iter of Btree
+    /// Get the references of the stored key and the value by key
     ///
     /// # Example
     ///
@@ -1427,44 +3489,18 @@ impl<K: Ord, V> BTree<K, V> {
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// let mut iter = b.iter();
-    /// assert_eq!(iter.next(), Some((&1, &1)));
-    /// assert_eq!(iter.next_back(), Some((&3, &3)));
-    /// ```      
-    pub fn iter<'a>(&'a self) -> Iter<'a, K, V> {
-        let seen = HashSet::new();
-        let seen_back = HashSet::new();
-        let next_back_nodes_start = if self.root_node.is_some() {
-            Node::get_data_size(self.root_node) - 1
-        } else {
-            0
-        };
-        if self.len > 0 {
-            Iter {
-                next_nodes: vec![NextNodes {
-                    node: self.root_node,
-                    index: Cell::new(0),
-                }],
-                seen: seen,
-                next_back_nodes: vec![NextNodes {
-                    node: self.root_node,
-                    index: Cell::new(next_back_nodes_start),
-                }],
-                seen_back: seen_back,
-                _marker: PhantomData,
-            }
-        } else {
-            Iter {
-                next_nodes: Vec::new(),
-                seen: seen,
-                next_back_nodes: Vec::new(),
-                seen_back: seen_back,
-                _marker: PhantomData,
-            }
-        }
+    /// assert_eq!(b.get_key_value(&2), Some((&2, &2)));
+    /// assert_eq!(b.get_key_value(&4), None);
+    /// ```
+    pub fn get_key_value<Q>(&self, k: &Q) -> Option<(&K, &V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self._get_key_value(k)
     }
 
-    /// Get the length
+    /// Removing by key and returning the stored key and the value
     ///
     /// # Example
     ///
@@ -1475,13 +3511,21 @@ impl<K: Ord, V> BTree<K, V> {
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// assert_eq!(b.len(), 3);
-    /// ```      
-    pub fn len(&self) -> usize {
-        self.len
+    /// assert_eq!(b.remove_entry(&2), Some((2, 2)));
+    /// assert_eq!(b.remove_entry(&2), None);
+    /// assert_eq!(b.len(), 2);
+    /// ```
+    pub fn remove_entry<Q>(&mut self, k: &Q) -> Option<(K, V)>
+    where
+        K: Borrow<Q>,
+        Q: Ord + ?Sized,
+    {
+        self._remove(k).map(|d| (d.key, d.value))
     }
 
-    /// To tell if this tree is empty
+    /// Provides an iterator which pops out the key-value pairs
+    /// in ascending order, the tree becomes empty after the iterator
+    /// is dropped even if it's not fully consumed
     ///
     /// # Example
     ///
@@ -1492,27 +3536,213 @@ impl<K: Ord, V> BTree<K, V> {
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// assert!(!b.is_empty());
-    /// ```      
-    pub fn is_empty(&self) -> bool {
-        self.len == 0
+    /// assert_eq!(b.drain().next(), Some((1, 1)));
+    /// assert!(b.is_empty());
+    /// ```
+    pub fn drain(&mut self) -> Drain<'_, K, V> {
+        Drain(self)
     }
 
-    /// Clearing the tree
+    /// Consuming the tree and returning all the
+    /// key-value pairs in ascending order
     ///
     /// # Example
     ///
     /// ```
     /// use ABtree::BTree;
     /// let mut b: BTree<i32, i32> = BTree::new(4);
-    /// let data = [(1, 1), (2, 2), (3, 3)];
+    /// let data = [(2, 2), (1, 1), (3, 3)];
     /// for (k, v) in data {
     ///     b.insert(k, v)
     /// }
-    /// b.clear();
-    /// assert_eq!(b.len(), 0);
-    /// ```      
-    pub fn clear(&mut self) {
-        *self = Self::new(self.max_key_num);
+    /// assert_eq!(b.into_sorted_vec(), vec![(1, 1), (2, 2), (3, 3)]);
+    /// ```
+    pub fn into_sorted_vec(self) -> Vec<(K, V)> {
+        let mut out = Vec::with_capacity(self.len);
+        out.extend(self);
+        out
+    }
+
+    /// Building a B-tree with some order from key-value pairs which are
+    /// sorted in ascending order without duplicated keys, the nodes are
+    /// packed level by level in O(n) time instead of inserting one by one
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let b = BTree::from_sorted(4, (0..100).map(|i| (i, i)));
+    /// assert_eq!(b.len(), 100);
+    /// assert_eq!(b.get(&42), Some(&42));
+    /// assert_eq!(b.validate(), Ok(()));
+    /// ```
+    pub fn from_sorted<I: IntoIterator<Item = (K, V)>>(order: usize, pairs: I) -> Self {
+        let mut out = BTree::new(order);
+        let items: VecDeque<Data<K, V>> = pairs
+            .into_iter()
+            .map(|(key, value)| Data { key, value })
+            .collect();
+        debug_assert!(
+            items
+                .iter()
+                .zip(items.iter().skip(1))
+                .all(|(a, b)| a.key < b.key),
+            "the pairs should be sorted by key without duplicates"
+        );
+        if !items.is_empty() {
+            out.len = items.len();
+            out.root_node = out._pack_sorted(items);
+        }
+        out
+    }
+
+    /// Making a new B-tree of some order with the keys in either tree,
+    /// the value of `other` wins if a key is in both trees
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let a = BTree::from_sorted(4, vec![(1, 'a'), (2, 'a')]);
+    /// let b = BTree::from_sorted(4, vec![(2, 'b'), (3, 'b')]);
+    /// let u = a.union(&b, 3);
+    /// assert_eq!(u.into_sorted_vec(), vec![(1, 'a'), (2, 'b'), (3, 'b')]);
+    /// ```
+    pub fn union(&self, other: &BTree<K, V>, order: usize) -> BTree<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let merged = merge::merge_sorted(self.iter(), other.iter(), SetOp::Union);
+        BTree::from_sorted(order, merged)
+    }
+
+    /// Making a new B-tree of some order with the keys in both trees
+    /// and the values are taken from `self`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let a = BTree::from_sorted(4, vec![(1, 'a'), (2, 'a')]);
+    /// let b = BTree::from_sorted(4, vec![(2, 'b'), (3, 'b')]);
+    /// let i = a.intersection(&b, 3);
+    /// assert_eq!(i.into_sorted_vec(), vec![(2, 'a')]);
+    /// ```
+    pub fn intersection(&self, other: &BTree<K, V>, order: usize) -> BTree<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let merged = merge::merge_sorted(self.iter(), other.iter(), SetOp::Intersection);
+        BTree::from_sorted(order, merged)
+    }
+
+    /// Making a new B-tree of some order with
+    /// the keys in `self` but not in `other`
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let a = BTree::from_sorted(4, vec![(1, 'a'), (2, 'a')]);
+    /// let b = BTree::from_sorted(4, vec![(2, 'b'), (3, 'b')]);
+    /// let d = a.difference(&b, 3);
+    /// assert_eq!(d.into_sorted_vec(), vec![(1, 'a')]);
+    /// ```
+    pub fn difference(&self, other: &BTree<K, V>, order: usize) -> BTree<K, V>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let merged = merge::merge_sorted(self.iter(), other.iter(), SetOp::Difference);
+        BTree::from_sorted(order, merged)
+    }
+
+    /// Getting the mutable reference of the value by key,
+    /// if the key is not in the tree the value made by `f`
+    /// is inserted first
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(4);
+    /// *b.get_or_insert_with(1, || 0) += 10;
+    /// *b.get_or_insert_with(1, || 0) += 10;
+    /// assert_eq!(b.get(&1), Some(&20));
+    /// ```
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, k: K, f: F) -> &mut V {
+        let (node, idx) = if self.root_node.is_none() {
+            self._add(k, f());
+            (self.root_node, 0)
+        } else {
+            // walking down once and inserting into the leaf,
+            // then the new key is followed through the splits
+            let mut cur_node = self.root_node;
+            loop {
+                let data = Node::get_inner_data(cur_node).unwrap();
+                let data = unsafe { &*data.as_ptr() };
+                let idx = data.iter().take_while(|d| d.key < k).count();
+                if data.get(idx).is_some_and(|d| d.key == k) {
+                    break (cur_node, idx);
+                }
+                if Node::get_children_size(cur_node) == 0 {
+                    self.len += 1;
+                    Node::insert_data(cur_node, idx, Some(Data { key: k, value: f() }));
+                    let (_, pos) = self._up_merging_tracked(cur_node, Some((cur_node, idx)));
+                    break pos.unwrap();
+                }
+                cur_node = Node::get_child_by_index(cur_node, idx);
+            }
+        };
+        let data = Node::get_inner_data(node).unwrap();
+        unsafe { &mut (&mut *data.as_ptr())[idx].value }
+    }
+
+    /// Counting the keys inside a range without visiting them,
+    /// it takes O(height * order) time by the subtree sizes
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let b = BTree::from_sorted(4, (0..10).map(|i| (i, i)));
+    /// assert_eq!(b.count_range(3..7), 4);
+    /// assert_eq!(b.count_range(..=3), 4);
+    /// assert_eq!(b.count_range(20..), 0);
+    /// ```
+    pub fn count_range<R: RangeBounds<K>>(&self, range: R) -> usize {
+        query::count_range(range, self.len, |k| self._count_less(k))
+    }
+
+    /// Get the keys held by each node in breadth-first order,
+    /// starting from the root and going level by level from left to right
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use ABtree::BTree;
+    /// let mut b: BTree<i32, i32> = BTree::new(3);
+    /// for i in 0..4 {
+    ///     b.insert(i, i)
+    /// }
+    /// assert_eq!(b.node_keys(), vec![vec![&1], vec![&0], vec![&2, &3]]);
+    /// ```
+    pub fn node_keys(&self) -> Vec<Vec<&K>> {
+        let mut out = Vec::new();
+        let mut queue = VecDeque::new();
+        if self.root_node.is_some() {
+            queue.push_back(self.root_node);
+        }
+        while let Some(cur_node) = queue.pop_front() {
+            if let Some(data) = Node::get_inner_data(cur_node) {
+                out.push(unsafe { (*data.as_ptr()).iter().map(|d| &d.key).collect() });
+            }
+            for idx in 0..Node::get_children_size(cur_node) {
+                queue.push_back(Node::get_child_by_index(cur_node, idx));
+            }
+        }
+        out
     }
 }
@@ -0,0 +1,88 @@
+//! Helpers for the flat binary layout used by `to_bytes` and `from_bytes`
+//! Every integer is little endian and every key or value is stored
+//! as a chunk prefixed with it's length as u32
+use alloc::vec::Vec;
+use core::convert::{TryFrom, TryInto};
+
+/// Pushing a u64 into the output
+#[inline]
+pub(crate) fn write_u64(out: &mut Vec<u8>, n: u64) {
+    out.extend_from_slice(&n.to_le_bytes());
+}
+
+/// Pushing a chunk prefixed with it's length into the output
+/// Panics if the chunk is longer than `u32::MAX` bytes
+#[inline]
+pub(crate) fn write_chunk(out: &mut Vec<u8>, chunk: &[u8]) {
+    let len = u32::try_from(chunk.len()).expect("chunk is longer than u32::MAX bytes");
+    out.extend_from_slice(&len.to_le_bytes());
+    out.extend_from_slice(chunk);
+}
+
+/// A cursor reading back what has been written by
+/// `write_u64` and `write_chunk`
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, pos: 0 }
+    }
+
+    /// Taking the next n bytes, returns None if there are not enough bytes
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        let end = self.pos.checked_add(n)?;
+        let out = self.bytes.get(self.pos..end)?;
+        self.pos = end;
+        Some(out)
+    }
+
+    pub(crate) fn read_u64(&mut self) -> Option<u64> {
+        self.take(8)
+            .map(|b| u64::from_le_bytes(b.try_into().unwrap()))
+    }
+
+    pub(crate) fn read_chunk(&mut self) -> Option<&'a [u8]> {
+        let len = self
+            .take(4)
+            .map(|b| u32::from_le_bytes(b.try_into().unwrap()))?;
+        self.take(len as usize)
+    }
+
+    /// To tell if all the bytes have been read
+    pub(crate) fn is_end(&self) -> bool {
+        self.pos == self.bytes.len()
+    }
+
+    /// Reading `len` key-value pairs up to the end of the bytes,
+    /// returns None if any decoder fails, the keys are not strictly
+    /// ascending or there are bytes left over
+    pub(crate) fn read_sorted_pairs<K, V, FK, FV>(
+        mut self,
+        len: u64,
+        decode_key: FK,
+        decode_value: FV,
+    ) -> Option<Vec<(K, V)>>
+    where
+        K: Ord,
+        FK: Fn(&[u8]) -> Option<K>,
+        FV: Fn(&[u8]) -> Option<V>,
+    {
+        let mut out: Vec<(K, V)> = Vec::new();
+        for _ in 0..len {
+            let k = decode_key(self.read_chunk()?)?;
+            let v = decode_value(self.read_chunk()?)?;
+            if out.last().is_some_and(|(last, _)| *last >= k) {
+                return None;
+            }
+            out.push((k, v));
+        }
+        if self.is_end() {
+            Some(out)
+        } else {
+            None
+        }
+    }
+}
@@ -3,19 +3,58 @@
 //! For the Btree module, what makes it different from that of BtreeMap in std
 //! is this Btree can accept any number as the maximum number of inner node, as long
 //! as the number grater or equal to 3
+//! Without the default `std` feature the crate is `no_std`
+//! and only needs the `alloc` crate
+
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+extern crate alloc;
 
 mod A;
 mod B;
+#[cfg(feature = "bytes")]
+mod bytes;
+mod merge;
+mod query;
 
-pub use A::AVL::AVL;
-pub use B::Btree::BTree;
+pub use A::AVL::{AVLValidationError, AVL};
+pub use B::Btree::{BTree, OccupiedEntry, ValidationError};
 
 #[cfg(test)]
 mod tests {
+    use std::alloc::{GlobalAlloc, Layout, System};
+    use std::cell::Cell;
+    use std::collections::BTreeMap;
     use std::iter::FromIterator;
 
-    use crate::A::AVL::AVL;
-    use crate::B::Btree::BTree;
+    use crate::A::AVL::{AVLValidationError, AVL};
+    use crate::B::Btree::{BTree, ValidationError};
+
+    thread_local! {
+        static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    }
+
+    /// Counting the allocations of each test thread
+    struct CountingAlloc;
+
+    unsafe impl GlobalAlloc for CountingAlloc {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let _ = ALLOCATIONS.try_with(|a| a.set(a.get() + 1));
+            System.alloc(layout)
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+            System.dealloc(ptr, layout)
+        }
+    }
+
+    #[global_allocator]
+    static GLOBAL: CountingAlloc = CountingAlloc;
+
+    fn allocations() -> usize {
+        ALLOCATIONS.with(|a| a.get())
+    }
+
     #[test]
     fn avl_len() {
         let data = vec![
@@ -189,4 +228,2286 @@ mod tests {
         assert_eq!(iter.next(), None);
         assert_eq!(iter.next_back(), None);
     }
+
+    #[test]
+    fn avl_remove_sorted() {
+        let mut avl = AVL::from_iter((0..10).map(|i| (i, i)));
+        assert_eq!(avl.remove_sorted(&[2, 5, 8]), 3);
+        assert_eq!(avl.len(), 7);
+        assert!(avl.is_balanced_tree());
+        assert_eq!(avl.validate(), Ok(()));
+        assert_eq!(avl.remove_sorted(&[-1, 2, 3, 3, 20]), 1);
+        assert_eq!(avl.remove_sorted(&[]), 0);
+        assert_eq!(avl.validate(), Ok(()));
+        let v: Vec<_> = avl.iter().map(|(k, _)| *k).collect();
+        assert_eq!(v, vec![0, 1, 4, 6, 7, 9]);
+        assert_eq!(avl.remove_sorted(&v), 6);
+        assert!(avl.is_empty());
+        assert_eq!(avl.validate(), Ok(()));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "sorted in ascending order")]
+    fn avl_remove_sorted_unsorted_keys() {
+        let mut avl = AVL::from_iter((0..10).map(|i| (i, i)));
+        avl.remove_sorted(&[5, 2]);
+    }
+
+    #[test]
+    fn btree_remove_sorted() {
+        let mut btr = BTree::new(3);
+        for i in 0..10 {
+            btr.insert(i, i);
+        }
+        assert_eq!(btr.remove_sorted(&[2, 5, 8]), 3);
+        assert_eq!(btr.len(), 7);
+        let v: Vec<_> = btr.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(v, vec![0, 1, 3, 4, 6, 7, 9]);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "sorted in ascending order")]
+    fn btree_remove_sorted_unsorted_keys() {
+        let mut btr = BTree::from_sorted(3, (0..10).map(|i| (i, i)));
+        btr.remove_sorted(&[5, 2]);
+    }
+
+    #[test]
+    fn avl_clone_range() {
+        let avl = AVL::from_iter((0..100).map(|i| (i, i)));
+        let c = avl.clone_range(20..30);
+        assert_eq!(c.len(), 10);
+        assert_eq!(avl.len(), 100);
+        assert_eq!(c.validate(), Ok(()));
+        assert!(avl.clone_range(200..).is_empty());
+        assert_eq!(avl.clone_range(..=4).len(), 5);
+        let v: Vec<_> = c.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(v, (20..30).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn btree_clone_range() {
+        let btr = BTree::from_iter((0..100).map(|i| (i, i)));
+        let c = btr.clone_range(20..30);
+        assert_eq!(c.len(), 10);
+        assert_eq!(btr.len(), 100);
+        assert_eq!(c.order(), btr.order());
+        assert_eq!(c.validate(), Ok(()));
+        assert!(btr.clone_range(200..).is_empty());
+        assert_eq!(btr.clone_range(..=4).len(), 5);
+        let v: Vec<_> = c.into_iter().map(|(k, _)| k).collect();
+        assert_eq!(v, (20..30).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn avl_insert_counting() {
+        let mut avl = AVL::new();
+        assert_eq!(avl.insert_counting(1, 1), 1);
+        assert_eq!(avl.insert_counting(2, 2), 2);
+        // inserting 3 makes the tree unbalanced and triggers a rotation
+        assert!(avl.insert_counting(3, 3) > 3);
+        assert!(avl.is_balanced_tree());
+    }
+
+    #[test]
+    fn btree_insert_counting() {
+        let mut btr = BTree::new(3);
+        assert_eq!(btr.insert_counting(1, 1), 1);
+        assert_eq!(btr.insert_counting(2, 2), 1);
+        // the root node is full and splitting it creates two more nodes
+        assert!(btr.insert_counting(3, 3) > 1);
+        assert_eq!(btr.len(), 3);
+    }
+
+    #[test]
+    fn avl_group_by_value() {
+        let data = vec![(1, 20), (2, 10), (3, 20), (4, 30), (5, 10), (6, 20)];
+        let avl = AVL::from_iter(data);
+        let groups: Vec<_> = avl.group_by_value().collect();
+        assert_eq!(
+            groups,
+            vec![
+                (&10, vec![&2, &5]),
+                (&20, vec![&1, &3, &6]),
+                (&30, vec![&4])
+            ]
+        );
+    }
+
+    #[test]
+    fn btree_group_by_value() {
+        let data = vec![(1, 20), (2, 10), (3, 20), (4, 30), (5, 10), (6, 20)];
+        let btr = BTree::from_iter(data);
+        let groups: Vec<_> = btr.group_by_value().collect();
+        assert_eq!(
+            groups,
+            vec![
+                (&10, vec![&2, &5]),
+                (&20, vec![&1, &3, &6]),
+                (&30, vec![&4])
+            ]
+        );
+    }
+
+    #[test]
+    fn avl_offset_from() {
+        let avl = AVL::from_iter((0..10).map(|i| (i, i * 10)));
+        assert_eq!(avl.offset_from(&5, 2), Some((&7, &70)));
+        assert_eq!(avl.offset_from(&5, -1), Some((&4, &40)));
+        assert_eq!(avl.offset_from(&5, 0), Some((&5, &50)));
+        assert_eq!(avl.offset_from(&5, 5), None);
+        assert_eq!(avl.offset_from(&5, -6), None);
+        assert_eq!(avl.offset_from(&50, 1), None);
+        assert_eq!(avl.offset_from(&5, isize::MAX), None);
+        assert_eq!(avl.offset_from(&5, isize::MIN), None);
+    }
+
+    #[test]
+    fn btree_offset_from() {
+        let btr = BTree::from_iter((0..10).map(|i| (i, i * 10)));
+        assert_eq!(btr.offset_from(&5, 2), Some((&7, &70)));
+        assert_eq!(btr.offset_from(&5, -1), Some((&4, &40)));
+        assert_eq!(btr.offset_from(&5, 0), Some((&5, &50)));
+        assert_eq!(btr.offset_from(&5, 5), None);
+        assert_eq!(btr.offset_from(&5, -6), None);
+        assert_eq!(btr.offset_from(&50, 1), None);
+        assert_eq!(btr.offset_from(&5, isize::MAX), None);
+        assert_eq!(btr.offset_from(&5, isize::MIN), None);
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn btree_bytes_round_trip() {
+        use std::convert::TryInto;
+
+        let mut btr: BTree<u32, u32> = BTree::new(4);
+        for i in 0..50 {
+            btr.insert(i, i * 3);
+        }
+        let encode = |n: &u32| n.to_le_bytes().to_vec();
+        let decode = |b: &[u8]| b.try_into().ok().map(u32::from_le_bytes);
+        let bytes = btr.to_bytes(encode, encode);
+        assert_eq!(bytes.len(), 8 + 8 + 50 * (4 + 4 + 4 + 4));
+
+        let decoded = BTree::from_bytes(&bytes, decode, decode).unwrap();
+        assert_eq!(decoded.len(), 50);
+        let v: Vec<_> = decoded.into_iter().collect();
+        assert_eq!(v, (0..50).map(|i| (i, i * 3)).collect::<Vec<_>>());
+
+        assert!(BTree::from_bytes(&bytes[..bytes.len() - 1], decode, decode).is_none());
+
+        let mut huge_order = bytes.clone();
+        huge_order[..8].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(BTree::from_bytes(&huge_order, decode, decode).is_none());
+
+        // the header says 2 entries but both of them have the key 7
+        let mut dup = Vec::new();
+        dup.extend_from_slice(&4u64.to_le_bytes());
+        dup.extend_from_slice(&2u64.to_le_bytes());
+        for v in [1u32, 2] {
+            dup.extend_from_slice(&4u32.to_le_bytes());
+            dup.extend_from_slice(&7u32.to_le_bytes());
+            dup.extend_from_slice(&4u32.to_le_bytes());
+            dup.extend_from_slice(&v.to_le_bytes());
+        }
+        assert!(BTree::from_bytes(&dup, decode, decode).is_none());
+        assert!(AVL::from_bytes(&dup[8..], decode, decode).is_none());
+
+        let mut unsorted = dup.clone();
+        unsorted[20..24].copy_from_slice(&9u32.to_le_bytes());
+        assert!(BTree::from_bytes(&unsorted, decode, decode).is_none());
+        assert!(AVL::from_bytes(&unsorted[8..], decode, decode).is_none());
+    }
+
+    #[test]
+    fn avl_get_mut() {
+        let mut avl = AVL::new();
+        for k in [3, 1, 2, 1, 3, 1] {
+            match avl.get_mut(&k) {
+                Some(count) => *count += 1,
+                None => avl.insert(k, 1),
+            }
+        }
+        assert_eq!(avl.get(&1), Some(&3));
+        assert_eq!(avl.get(&2), Some(&1));
+        assert_eq!(avl.get(&3), Some(&2));
+        assert_eq!(avl.get_mut(&4), None);
+    }
+
+    #[test]
+    fn btree_fill_factor() {
+        let empty: BTree<i32, i32> = BTree::new(5);
+        assert_eq!(empty.fill_factor(), 0.0);
+
+        // four keys packed in the root node
+        let mut packed = BTree::new(5);
+        for i in 0..4 {
+            packed.insert(i, i);
+        }
+        assert_eq!(packed.fill_factor(), 0.8);
+
+        // ascending inserts keep splitting and leave half full nodes behind
+        let mut sparse = BTree::new(5);
+        for i in 0..1000 {
+            sparse.insert(i, i);
+        }
+        for i in 0..1000 {
+            if i % 3 != 0 {
+                sparse.remove(&i);
+            }
+        }
+        let ff = sparse.fill_factor();
+        assert!(ff > 0.0 && ff < packed.fill_factor());
+
+        // random inserts and deletes against the same keys packed by from_sorted
+        let mut random = BTree::new(5);
+        let mut seed: u64 = 11;
+        for i in 0..6000 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            let k = (seed >> 33) % 3000;
+            if i % 3 == 2 {
+                random.remove(&k);
+            } else {
+                random.insert(k, k);
+            }
+        }
+        let bulk = BTree::from_sorted(5, random.iter().map(|(k, v)| (*k, *v)));
+        assert_eq!(bulk.len(), random.len());
+        assert!(bulk.fill_factor() > random.fill_factor());
+    }
+
+    #[test]
+    fn btree_get_mut() {
+        let mut btr = BTree::new(3);
+        for i in 0..20 {
+            btr.insert(i, i);
+        }
+        for i in 0..20 {
+            if let Some(v) = btr.get_mut(&i) {
+                *v *= 10;
+            }
+        }
+        for i in 0..20 {
+            assert_eq!(btr.get(&i), Some(&(i * 10)));
+        }
+        assert_eq!(btr.get_mut(&20), None);
+        assert_eq!(btr.len(), 20);
+    }
+
+    #[test]
+    fn avl_iter_by_value() {
+        let data = vec![(1, 5), (2, 9), (3, 1), (4, 9), (5, 7)];
+        let avl: AVL<i32, i32> = AVL::from_iter(data);
+        let v: Vec<_> = avl.iter_by_value(|a, b| b.cmp(a)).collect();
+        assert_eq!(v, vec![(&2, &9), (&4, &9), (&5, &7), (&1, &5), (&3, &1)]);
+        assert_eq!(avl.len(), 5);
+    }
+
+    #[test]
+    fn avl_iter_mut() {
+        let mut avl = AVL::from_iter((0..50).map(|i| (i, i)));
+        for (_, v) in avl.iter_mut() {
+            *v *= 2;
+        }
+        let keys: Vec<_> = avl.iter_mut().map(|(k, _)| *k).collect();
+        assert_eq!(keys, (0..50).collect::<Vec<_>>());
+        let v: Vec<_> = avl.into_iter().collect();
+        assert_eq!(v, (0..50).map(|i| (i, i * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn avl_peekable_iter() {
+        let avl = AVL::from_iter((0..10).map(|i| (i, i)));
+        let mut iter = avl.peekable_iter();
+        assert_eq!(iter.peek(), Some((&0, &0)));
+        assert_eq!(iter.peek_back(), Some((&9, &9)));
+        assert_eq!(iter.peek(), Some((&0, &0)));
+        assert_eq!(iter.next(), Some((&0, &0)));
+        assert_eq!(iter.next_back(), Some((&9, &9)));
+        assert_eq!(iter.peek(), Some((&1, &1)));
+        let rest: Vec<_> = iter.map(|(k, _)| *k).collect();
+        assert_eq!(rest, (1..9).collect::<Vec<_>>());
+
+        let empty: AVL<i32, i32> = AVL::new();
+        assert_eq!(empty.peekable_iter().peek(), None);
+        assert_eq!(empty.peekable_iter().peek_back(), None);
+    }
+
+    #[test]
+    fn btree_peekable_iter() {
+        let btr = BTree::from_iter((0..10).map(|i| (i, i)));
+        let mut iter = btr.peekable_iter();
+        assert_eq!(iter.peek(), Some((&0, &0)));
+        assert_eq!(iter.peek_back(), Some((&9, &9)));
+        assert_eq!(iter.next(), Some((&0, &0)));
+        assert_eq!(iter.next_back(), Some((&9, &9)));
+        assert_eq!(iter.peek(), Some((&1, &1)));
+        assert_eq!(iter.peek_back(), Some((&8, &8)));
+        let rest: Vec<_> = iter.map(|(k, _)| *k).collect();
+        assert_eq!(rest, (1..9).collect::<Vec<_>>());
+
+        let empty: BTree<i32, i32> = BTree::new(3);
+        assert_eq!(empty.peekable_iter().peek(), None);
+        assert_eq!(empty.peekable_iter().peek_back(), None);
+    }
+
+    #[test]
+    fn btree_iter_mut() {
+        let mut btr = BTree::new(4);
+        for i in 0..50 {
+            btr.insert(i, i);
+        }
+        for (_, v) in btr.iter_mut() {
+            *v *= 3;
+        }
+        let v: Vec<_> = btr.into_iter().collect();
+        assert_eq!(v, (0..50).map(|i| (i, i * 3)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn avl_longest_increasing_value_run() {
+        let values = [1, 2, 3, 2, 4, 5, 6];
+        let avl = AVL::from_iter(values.iter().enumerate().map(|(k, v)| (k, *v)));
+        assert_eq!(avl.longest_increasing_value_run(), 4);
+        let empty: AVL<i32, i32> = AVL::new();
+        assert_eq!(empty.longest_increasing_value_run(), 0);
+    }
+
+    #[test]
+    fn btree_longest_increasing_value_run() {
+        let values = [1, 2, 3, 2, 4, 5, 6];
+        let btr = BTree::from_iter(values.iter().enumerate().map(|(k, v)| (k, *v)));
+        assert_eq!(btr.longest_increasing_value_run(), 4);
+        let empty: BTree<i32, i32> = BTree::new(3);
+        assert_eq!(empty.longest_increasing_value_run(), 0);
+    }
+
+    #[test]
+    fn avl_values_mut() {
+        let mut avl = AVL::from_iter((0..10).map(|i| (i, i)));
+        for (n, v) in avl.values_mut().enumerate() {
+            *v = n as i32 * 100;
+        }
+        let v: Vec<_> = avl.into_iter().collect();
+        assert_eq!(v, (0..10).map(|i| (i, i * 100)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn avl_pop_min_n() {
+        let mut avl = AVL::from_iter((0..10).map(|i| (i, i)));
+        assert_eq!(avl.pop_min_n(3), vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(avl.len(), 7);
+        assert!(avl.is_balanced_tree());
+        let v: Vec<_> = avl.iter().map(|(k, _)| *k).collect();
+        assert_eq!(v, (3..10).collect::<Vec<_>>());
+        assert_eq!(avl.pop_min_n(20).len(), 7);
+        assert!(avl.is_empty());
+    }
+
+    #[test]
+    fn btree_pop_min_n() {
+        let mut btr = BTree::from_iter((0..10).map(|i| (i, i)));
+        assert_eq!(btr.pop_min_n(3), vec![(0, 0), (1, 1), (2, 2)]);
+        assert_eq!(btr.len(), 7);
+        let v: Vec<_> = btr.iter().map(|(k, _)| *k).collect();
+        assert_eq!(v, (3..10).collect::<Vec<_>>());
+        assert_eq!(btr.pop_min_n(20).len(), 7);
+        assert!(btr.is_empty());
+    }
+
+    #[test]
+    fn btree_keys_values() {
+        let btr = BTree::from_iter((0..30).rev().map(|i| (i, i * 2)));
+        let keys: Vec<_> = btr.keys().cloned().collect();
+        assert_eq!(keys, (0..30).collect::<Vec<_>>());
+        let values: Vec<_> = btr.values().cloned().collect();
+        assert_eq!(values, (0..30).map(|i| i * 2).collect::<Vec<_>>());
+        let empty: BTree<i32, i32> = BTree::new(3);
+        assert_eq!(empty.keys().next(), None);
+        assert_eq!(empty.values().next(), None);
+    }
+
+    #[test]
+    fn btree_append_sorted_run() {
+        let mut btr = BTree::new(4);
+        for i in 0..100 {
+            btr.insert(i, i);
+        }
+        btr.append_sorted_run((100..200).map(|i| (i, i)));
+        assert_eq!(btr.len(), 200);
+        let keys: Vec<_> = btr.keys().cloned().collect();
+        assert_eq!(keys, (0..200).collect::<Vec<_>>());
+        let rev: Vec<_> = btr.iter().rev().map(|(k, _)| *k).collect();
+        assert_eq!(rev, (0..200).rev().collect::<Vec<_>>());
+        for i in 0..200 {
+            assert_eq!(btr.get(&i), Some(&i));
+        }
+
+        // keys not above the maximum fall back to inserting
+        btr.append_sorted_run(vec![(50, 500), (300, 300)]);
+        assert_eq!(btr.len(), 201);
+        assert_eq!(btr.get(&50), Some(&500));
+        assert_eq!(btr.get(&300), Some(&300));
+        assert_eq!(btr.validate(), Ok(()));
+
+        // the cached sizes on the right spine are right after the run
+        for order in [3, 4, 7] {
+            let mut b = BTree::new(order);
+            b.append_sorted_run((0..500).map(|i| (i * 2, i)));
+            assert_eq!(b.validate(), Ok(()));
+            assert!(b.node_keys().iter().all(|n| n.len() < order));
+            b.append_sorted_run((995..1500).map(|i| (i, i)));
+            assert_eq!(b.len(), 1003);
+            assert_eq!(b.validate(), Ok(()));
+            assert_eq!(b.rank(&1200), Some(703));
+        }
+    }
+
+    #[test]
+    fn avl_keys_values() {
+        let avl = AVL::from_iter((0..30).rev().map(|i| (i, i * 2)));
+        let keys: Vec<_> = avl.keys().cloned().collect();
+        assert_eq!(keys, (0..30).collect::<Vec<_>>());
+        let rev_keys: Vec<_> = avl.keys().rev().cloned().collect();
+        assert_eq!(rev_keys, (0..30).rev().collect::<Vec<_>>());
+        let values: Vec<_> = avl.values().cloned().collect();
+        assert_eq!(values, (0..30).map(|i| i * 2).collect::<Vec<_>>());
+        let rev_values: Vec<_> = avl.values().rev().cloned().collect();
+        assert_eq!(rev_values, (0..30).rev().map(|i| i * 2).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn btree_validate() {
+        let mut btr = BTree::new(3);
+        assert_eq!(btr.validate(), Ok(()));
+        for i in 0..100 {
+            btr.insert(i, i);
+        }
+        assert_eq!(btr.validate(), Ok(()));
+        for i in (0..100).step_by(3) {
+            btr.remove(&i);
+        }
+        assert_eq!(btr.validate(), Ok(()));
+
+        btr._test_set_len(10);
+        assert_eq!(
+            btr.validate(),
+            Err(ValidationError::LenMismatch {
+                counted: 66,
+                stored: 10
+            })
+        );
+        btr._test_set_len(66);
+        assert_eq!(btr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn btree_validate_even_orders() {
+        for order in [4, 6, 8, 10] {
+            let mut btr = BTree::new(order);
+            for i in 0..500 {
+                btr.insert((i * 37) % 500, i);
+            }
+            assert_eq!(btr.validate(), Ok(()));
+            // splitting a full node of an even order
+            // leaves one key less in the right half
+            let nodes = btr.node_keys();
+            assert!(nodes[1..].iter().all(|n| n.len() >= order / 2 - 1));
+            for i in (0..500).step_by(3) {
+                btr.remove(&i);
+            }
+            assert_eq!(btr.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn btree_validate_size() {
+        let mut btr = BTree::from_iter((0..100).map(|i| (i, i)));
+        btr._test_set_root_size(7);
+        assert_eq!(
+            btr.validate(),
+            Err(ValidationError::SizeMismatch {
+                depth: 0,
+                stored: 7,
+                expected: 100
+            })
+        );
+        btr._test_set_root_size(100);
+        assert_eq!(btr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn btree_validate_unsorted() {
+        let mut btr = BTree::from_iter((0..100).map(|i| (i, i)));
+        btr._test_swap_root_keys(0, 1);
+        assert!(matches!(
+            btr.validate(),
+            Err(ValidationError::UnsortedNode { depth: 0 })
+        ));
+        btr._test_swap_root_keys(0, 1);
+        assert_eq!(btr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn btree_validate_underfull() {
+        let mut btr = BTree::new(3);
+        for i in 0..20 {
+            btr.insert(i, i);
+        }
+        btr._test_pop_min_leaf_key();
+        assert!(matches!(
+            btr.validate(),
+            Err(ValidationError::UnderfullNode { keys: 0, .. })
+        ));
+        std::mem::forget(btr);
+    }
+
+    #[test]
+    fn btree_validate_child_count() {
+        let mut btr = BTree::new(3);
+        for i in 0..20 {
+            btr.insert(i, i);
+        }
+        btr._test_push_root_key(100, 100);
+        assert!(matches!(
+            btr.validate(),
+            Err(ValidationError::ChildCountMismatch { depth: 0, .. })
+        ));
+        std::mem::forget(btr);
+    }
+
+    #[test]
+    fn btree_validate_leaf_depth() {
+        let mut btr = BTree::new(3);
+        for i in 0..20 {
+            btr.insert(i, i);
+        }
+        btr._test_lift_last_grandchild();
+        assert!(matches!(
+            btr.validate(),
+            Err(ValidationError::UnequalLeafDepth { .. })
+        ));
+        std::mem::forget(btr);
+    }
+
+    #[test]
+    fn btree_validate_overfull() {
+        let mut btr = BTree::new(3);
+        for i in 0..20 {
+            btr.insert(i, i);
+        }
+        btr._test_push_min_leaf_key(-1, -1);
+        btr._test_push_min_leaf_key(-2, -2);
+        btr._test_push_min_leaf_key(-3, -3);
+        assert!(matches!(
+            btr.validate(),
+            Err(ValidationError::OverfullNode { .. })
+        ));
+        std::mem::forget(btr);
+    }
+
+    #[test]
+    fn btree_validate_parent() {
+        let mut btr = BTree::new(3);
+        for i in 0..20 {
+            btr.insert(i, i);
+        }
+        btr._test_break_first_child_parent();
+        let err = btr.validate().unwrap_err();
+        assert_eq!(err, ValidationError::ParentMismatch { depth: 1 });
+        assert_eq!(err.to_string(), "wrong parent pointer at depth 1");
+        std::mem::forget(btr);
+    }
+
+    #[test]
+    fn avl_validate() {
+        let mut t = AVL::new();
+        assert_eq!(t.validate(), Ok(()));
+        for i in 0..100 {
+            t.insert(i, i);
+        }
+        assert_eq!(t.validate(), Ok(()));
+        for i in (0..100).step_by(3) {
+            t.remove(&i);
+        }
+        assert_eq!(t.validate(), Ok(()));
+
+        t._test_set_len(10);
+        assert_eq!(
+            t.validate(),
+            Err(AVLValidationError::LenMismatch {
+                counted: 66,
+                stored: 10
+            })
+        );
+        t._test_set_len(66);
+
+        t._test_set_root_height(0);
+        let expected = match t.validate() {
+            Err(AVLValidationError::HeightMismatch {
+                depth: 0,
+                stored: 0,
+                expected,
+            }) => expected,
+            other => panic!("unexpected {:?}", other),
+        };
+        t._test_set_root_height(expected);
+        assert_eq!(t.validate(), Ok(()));
+    }
+
+    #[test]
+    fn avl_validate_unsorted() {
+        let mut t = AVL::from_iter((0..100).map(|i| (i, i)));
+        t._test_swap_root_left_keys();
+        assert!(matches!(
+            t.validate(),
+            Err(AVLValidationError::UnsortedNode { .. })
+        ));
+        t._test_swap_root_left_keys();
+        assert_eq!(t.validate(), Ok(()));
+    }
+
+    #[test]
+    fn avl_validate_parent() {
+        let mut t = AVL::from_iter((0..20).map(|i| (i, i)));
+        t._test_break_left_parent();
+        let err = t.validate().unwrap_err();
+        assert_eq!(err, AVLValidationError::ParentMismatch { depth: 1 });
+        assert_eq!(err.to_string(), "wrong parent pointer at depth 1");
+        std::mem::forget(t);
+    }
+
+    #[test]
+    fn avl_validate_unbalanced() {
+        let mut t = AVL::from_iter((0..20).map(|i| (i, i)));
+        t._test_cut_right_subtree();
+        assert!(matches!(
+            t.validate(),
+            Err(AVLValidationError::Unbalanced { depth: 0, .. })
+        ));
+        std::mem::forget(t);
+    }
+
+    #[test]
+    fn avl_from_array() {
+        let t = AVL::from([(3, "c"), (1, "a"), (2, "b"), (1, "z")]);
+        assert_eq!(t.len(), 3);
+        assert_eq!(t.get(&1), Some(&"z"));
+        assert_eq!(t.validate(), Ok(()));
+        let empty: AVL<i32, i32> = AVL::from([]);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn btree_from_array() {
+        let b = BTree::from([(3, "c"), (1, "a"), (2, "b"), (1, "z")]);
+        assert_eq!(b.len(), 3);
+        assert_eq!(b.order(), 5);
+        assert_eq!(b.get(&1), Some(&"z"));
+        assert_eq!(b.validate(), Ok(()));
+        let empty: BTree<i32, i32> = BTree::from([]);
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn avl_btreemap_conversion() {
+        let map: BTreeMap<i32, i32> = (0..100).map(|i| (i, i * 2)).collect();
+        let t = AVL::from(map.clone());
+        assert_eq!(t.len(), 100);
+        assert_eq!(t.validate(), Ok(()));
+        assert!(t.iter().eq(map.iter()));
+        assert_eq!(BTreeMap::from(t), map);
+    }
+
+    #[test]
+    fn btree_btreemap_conversion() {
+        let map: BTreeMap<i32, i32> = (0..100).map(|i| (i, i * 2)).collect();
+        let b = BTree::from(map.clone());
+        assert_eq!(b.len(), 100);
+        assert_eq!(b.order(), 5);
+        assert_eq!(b.validate(), Ok(()));
+        assert!(b.iter().eq(map.iter()));
+        assert_eq!(BTreeMap::from(b), map);
+    }
+
+    #[test]
+    fn avl_nth() {
+        let mut t = AVL::from_iter((0..200).map(|i| (i * 3, i)));
+        for i in (0..200).step_by(7) {
+            t.remove(&(i * 3));
+        }
+        for i in 0..=t.len() {
+            assert_eq!(t.nth(i), t.iter().nth(i));
+        }
+    }
+
+    #[test]
+    fn btree_nth() {
+        let mut b = BTree::with_order_from_iter(4, (0..200).map(|i| (i * 3, i)));
+        for i in (0..200).step_by(7) {
+            b.remove(&(i * 3));
+        }
+        for i in 0..=b.len() {
+            assert_eq!(b.nth(i), b.iter().nth(i));
+        }
+    }
+
+    #[test]
+    fn btree_change_order() {
+        let mut b = BTree::new(3);
+        for i in (0..300).rev() {
+            b.insert(i, i.to_string());
+        }
+        for order in [3, 4, 10, 64, 4] {
+            b.change_order(order);
+            assert_eq!(b.order(), order);
+            assert_eq!(b.len(), 300);
+            assert_eq!(b.validate(), Ok(()));
+            assert!(b.keys().copied().eq(0..300));
+        }
+        b.insert(300, "300".to_string());
+        b.remove(&0);
+        assert_eq!(b.validate(), Ok(()));
+
+        let mut empty: BTree<i32, i32> = BTree::new(3);
+        empty.change_order(7);
+        assert!(empty.is_empty());
+        assert_eq!(empty.order(), 7);
+    }
+
+    #[test]
+    #[should_panic]
+    fn btree_change_order_too_small() {
+        let mut b = BTree::from_iter((0..10).map(|i| (i, i)));
+        b.change_order(2);
+    }
+
+    #[test]
+    fn avl_get_disjoint_mut() {
+        let mut t = AVL::from_iter((0..50).map(|i| (i, i)));
+        if let Some((a, b)) = t.get_disjoint_mut(&10, &40) {
+            std::mem::swap(a, b);
+        }
+        assert_eq!(t.get(&10), Some(&40));
+        assert_eq!(t.get(&40), Some(&10));
+        assert_eq!(t.get_disjoint_mut(&7, &7), None);
+        assert_eq!(t.get_disjoint_mut(&7, &100), None);
+        assert_eq!(t.get_disjoint_mut(&-1, &7), None);
+    }
+
+    #[test]
+    fn btree_values_mut() {
+        let mut b = BTree::with_order_from_iter(3, (0..100).map(|i| (i, vec![i])));
+        for (i, v) in b.values_mut().enumerate() {
+            v.push(i as i32 * 2);
+        }
+        assert!(b.iter().all(|(k, v)| *v == vec![*k, *k * 2]));
+        assert_eq!(b.values_mut().count(), 100);
+    }
+
+    #[test]
+    fn avl_remove_range() {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+        use std::ops::RangeBounds;
+        let ranges = [
+            (Included(20), Excluded(60)),
+            (Excluded(20), Included(60)),
+            (Unbounded, Excluded(35)),
+            (Included(150), Unbounded),
+            (Included(500), Included(600)),
+            (Excluded(42), Excluded(43)),
+        ];
+        for range in ranges.iter() {
+            let mut t = AVL::from_iter((0..200).map(|i| (i, i)));
+            t.remove_range(*range);
+            let expected: Vec<i32> = (0..200).filter(|i| !range.contains(i)).collect();
+            assert!(t.keys().copied().eq(expected.iter().copied()));
+            assert_eq!(t.len(), expected.len());
+            assert_eq!(t.validate(), Ok(()));
+        }
+
+        let mut t = AVL::from_iter((0..200).map(|i| (i, i)));
+        t.remove_range(..);
+        assert!(t.is_empty());
+        assert_eq!(t.validate(), Ok(()));
+    }
+
+    #[test]
+    fn btree_remove_range() {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+        use std::ops::RangeBounds;
+        let ranges = [
+            (Included(20), Excluded(60)),
+            (Excluded(20), Included(60)),
+            (Unbounded, Excluded(35)),
+            (Included(150), Unbounded),
+            (Included(500), Included(600)),
+            (Excluded(42), Excluded(43)),
+        ];
+        for order in [3, 4, 16] {
+            for range in ranges.iter() {
+                let mut b = BTree::with_order_from_iter(order, (0..200).map(|i| (i, i)));
+                b.remove_range(*range);
+                let expected: Vec<i32> = (0..200).filter(|i| !range.contains(i)).collect();
+                assert!(b.keys().copied().eq(expected.iter().copied()));
+                assert_eq!(b.len(), expected.len());
+                assert_eq!(b.validate(), Ok(()));
+            }
+
+            let mut b = BTree::with_order_from_iter(order, (0..200).map(|i| (i, i)));
+            b.remove_range(..);
+            assert!(b.is_empty());
+            assert_eq!(b.validate(), Ok(()));
+            b.insert(1, 1);
+            assert_eq!(b.len(), 1);
+        }
+    }
+
+    #[test]
+    fn avl_to_dot() {
+        let empty: AVL<i32, i32> = AVL::new();
+        assert_eq!(empty.to_dot(), "digraph AVL {\n}\n");
+
+        let t = AVL::from_iter((0..3).map(|i| (i, i)));
+        let expected = "digraph AVL {\n\
+                        \x20   n0 [label=\"1\\nh=2\"];\n\
+                        \x20   n0 -> n1 [label=\"L\"];\n\
+                        \x20   n0 -> n2 [label=\"R\"];\n\
+                        \x20   n2 [label=\"2\\nh=1\"];\n\
+                        \x20   n1 [label=\"0\\nh=1\"];\n\
+                        }\n";
+        assert_eq!(t.to_dot(), expected);
+
+        let t = AVL::from_iter((0..100).map(|i| (i, i)));
+        let dot = t.to_dot();
+        assert_eq!(
+            dot.matches("[label=\"L\"]").count() + dot.matches("[label=\"R\"]").count(),
+            99
+        );
+
+        let quoted = AVL::from([("a\"b", 1)]);
+        assert!(quoted.to_dot().contains("n0 [label=\"a\\\"b\\nh=1\"];"));
+    }
+
+    #[test]
+    fn btree_node_keys() {
+        let empty: BTree<i32, i32> = BTree::new(3);
+        assert!(empty.node_keys().is_empty());
+
+        let b = BTree::from_sorted(3, (0..7).map(|i| (i, i)));
+        let nodes = b.node_keys();
+        assert_eq!(nodes.iter().map(|n| n.len()).sum::<usize>(), 7);
+        let mut all: Vec<i32> = nodes.iter().flatten().map(|k| **k).collect();
+        all.sort_unstable();
+        assert_eq!(all, (0..7).collect::<Vec<_>>());
+
+        for order in [3, 4, 16] {
+            let b = BTree::with_order_from_iter(order, (0..500).map(|i| (i, i)));
+            let nodes = b.node_keys();
+            assert!(nodes.iter().all(|n| n.len() <= order));
+            assert!(nodes.iter().all(|n| n.windows(2).all(|w| w[0] < w[1])));
+            assert_eq!(nodes.iter().map(|n| n.len()).sum::<usize>(), 500);
+        }
+    }
+
+    #[test]
+    fn avl_clone_from() {
+        let sources = [
+            AVL::new(),
+            AVL::from_iter((0..10).map(|i| (i, i.to_string()))),
+            AVL::from_iter((0..100).map(|i| (i * 2, i.to_string()))),
+        ];
+        for dst_len in [0, 5, 10, 60, 300] {
+            for src in sources.iter() {
+                let mut t = AVL::from_iter((0..dst_len).map(|i| (i + 1000, "x".to_string())));
+                t.clone_from(src);
+                assert_eq!(&t, src);
+                assert_eq!(t.validate(), Ok(()));
+                t.insert(-1, "new".to_string());
+                t.remove(&4);
+                assert_eq!(t.validate(), Ok(()));
+            }
+        }
+    }
+
+    #[test]
+    fn btree_first_last_entry() {
+        let mut empty: BTree<i32, i32> = BTree::new(3);
+        assert!(empty.first_entry().is_none());
+        assert!(empty.last_entry().is_none());
+
+        let mut b = BTree::with_order_from_iter(3, (0..100).map(|i| (i, i)));
+        for expected in 0..50 {
+            let mut e = b.first_entry().unwrap();
+            let (k, v) = (e.key(), e.get());
+            assert_eq!((*k, *v), (expected, expected));
+            *e.get_mut() += 1000;
+            assert_eq!(e.get(), &(expected + 1000));
+            assert_eq!(e.remove_entry(), (expected, expected + 1000));
+            assert_eq!(b.validate(), Ok(()));
+
+            let e = b.last_entry().unwrap();
+            assert_eq!(*e.key(), 99 - expected);
+            *e.into_mut() = -1;
+            assert_eq!(b.last_key_value(), Some((&(99 - expected), &-1)));
+            assert_eq!(b.last_entry().unwrap().remove(), -1);
+            assert_eq!(b.validate(), Ok(()));
+        }
+        assert!(b.is_empty());
+        assert!(b.first_entry().is_none());
+    }
+
+    #[test]
+    fn avl_iter_with_neighbors() {
+        let avl = AVL::from_iter([(1, 10), (2, 20), (3, 30)]);
+        let got: Vec<_> = avl.iter_with_neighbors().collect();
+        assert_eq!(
+            got,
+            vec![
+                (None, (&1, &10), Some(&2)),
+                (Some(&1), (&2, &20), Some(&3)),
+                (Some(&2), (&3, &30), None),
+            ]
+        );
+        let empty: AVL<i32, i32> = AVL::new();
+        assert_eq!(empty.iter_with_neighbors().count(), 0);
+    }
+
+    #[test]
+    fn btree_iter_with_neighbors() {
+        let btr = BTree::from_iter([(1, 10), (2, 20), (3, 30)]);
+        let got: Vec<_> = btr.iter_with_neighbors().collect();
+        assert_eq!(
+            got,
+            vec![
+                (None, (&1, &10), Some(&2)),
+                (Some(&1), (&2, &20), Some(&3)),
+                (Some(&2), (&3, &30), None),
+            ]
+        );
+        let single = BTree::from_iter([(7, 7)]);
+        assert_eq!(
+            single.iter_with_neighbors().collect::<Vec<_>>(),
+            vec![(None, (&7, &7), None)]
+        );
+    }
+
+    #[test]
+    fn btree_balance_score() {
+        let empty: BTree<i32, i32> = BTree::new(4);
+        assert_eq!(empty.balance_score(), 1.0);
+
+        let small = BTree::from_iter([(1, 1), (2, 2), (3, 3)]);
+        assert_eq!(small.balance_score(), 1.0);
+
+        // heavy pseudo-random deletes leave sparse nodes behind
+        let mut degraded = BTree::new(4);
+        for i in 0..4000u64 {
+            degraded.insert(i, i);
+        }
+        let mut seed: u64 = 7;
+        for _ in 0..6000 {
+            seed = seed
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            degraded.remove(&((seed >> 33) % 4000));
+        }
+        assert_eq!(degraded.validate(), Ok(()));
+
+        // the same keys packed level by level are as low as possible
+        let bulk = BTree::from_sorted(4, degraded.iter().map(|(k, v)| (*k, *v)));
+        assert_eq!(bulk.len(), degraded.len());
+        assert_eq!(bulk.balance_score(), 1.0);
+        assert!(bulk.balance_score() >= degraded.balance_score());
+    }
+
+    #[test]
+    fn avl_range() {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+        let avl = AVL::from_iter((0..100).map(|i| (i * 2, i)));
+        let keys = |r: Vec<(&i32, &i32)>| r.into_iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys(avl.range(10..16).collect()), vec![10, 12, 14]);
+        assert_eq!(keys(avl.range(9..=16).collect()), vec![10, 12, 14, 16]);
+        assert_eq!(keys(avl.range(..4).collect()), vec![0, 2]);
+        assert_eq!(keys(avl.range(195..).collect()), vec![196, 198]);
+        assert_eq!(avl.range(..).count(), 100);
+        assert_eq!(
+            keys(avl.range((Excluded(10), Included(14))).collect()),
+            vec![12, 14]
+        );
+        assert_eq!(
+            keys(avl.range((Excluded(10), Unbounded)).rev().take(2).collect()),
+            vec![198, 196]
+        );
+        let mut r = avl.range(0..7);
+        assert_eq!(r.next(), Some((&0, &0)));
+        assert_eq!(r.next_back(), Some((&6, &3)));
+        assert_eq!(r.next(), Some((&2, &1)));
+        assert_eq!(r.next_back(), Some((&4, &2)));
+        assert_eq!(r.next(), None);
+        assert_eq!(r.next_back(), None);
+        // empty ranges
+        assert_eq!(avl.range(11..12).count(), 0);
+        assert_eq!(avl.range(500..).count(), 0);
+        assert_eq!(avl.range((Excluded(10), Excluded(10))).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = avl.range(20..10).count();
+        assert_eq!(reversed, 0);
+        let empty: AVL<i32, i32> = AVL::new();
+        assert_eq!(empty.range(..).count(), 0);
+    }
+
+    #[test]
+    fn btree_range() {
+        use std::ops::Bound::{Excluded, Included, Unbounded};
+        let mut btr = BTree::new(3);
+        for i in 0..100 {
+            btr.insert(i * 2, i);
+        }
+        let keys = |r: Vec<(&i32, &i32)>| r.into_iter().map(|(k, _)| *k).collect::<Vec<_>>();
+        assert_eq!(keys(btr.range(10..16).collect()), vec![10, 12, 14]);
+        assert_eq!(keys(btr.range(9..=16).collect()), vec![10, 12, 14, 16]);
+        assert_eq!(keys(btr.range(..4).collect()), vec![0, 2]);
+        assert_eq!(keys(btr.range(195..).collect()), vec![196, 198]);
+        assert_eq!(keys(btr.range(..).collect()), keys(btr.iter().collect()));
+        assert_eq!(
+            keys(btr.range(..).rev().collect()),
+            keys(btr.iter().rev().collect())
+        );
+        assert_eq!(
+            keys(btr.range((Excluded(10), Excluded(16))).collect()),
+            vec![12, 14]
+        );
+        assert_eq!(
+            keys(btr.range((Excluded(10), Unbounded)).rev().take(2).collect()),
+            vec![198, 196]
+        );
+        let mut r = btr.range((Included(0), Included(6)));
+        assert_eq!(r.next(), Some((&0, &0)));
+        assert_eq!(r.next_back(), Some((&6, &3)));
+        assert_eq!(r.next(), Some((&2, &1)));
+        assert_eq!(r.next_back(), Some((&4, &2)));
+        assert_eq!(r.next(), None);
+        assert_eq!(r.next_back(), None);
+        // empty ranges and bounds outside all keys
+        assert_eq!(btr.range(11..12).count(), 0);
+        assert_eq!(btr.range(500..).count(), 0);
+        assert_eq!(btr.range(..-5).count(), 0);
+        assert_eq!(btr.range(-50..500).count(), 100);
+        assert_eq!(btr.range((Excluded(10), Excluded(10))).count(), 0);
+        #[allow(clippy::reversed_empty_ranges)]
+        let reversed = btr.range(20..10).count();
+        assert_eq!(reversed, 0);
+        let empty: BTree<i32, i32> = BTree::new(4);
+        assert_eq!(empty.range(..).count(), 0);
+    }
+
+    #[test]
+    fn btree_get_descends() {
+        for order in 3..8 {
+            let mut btr = BTree::new(order);
+            for i in (0..500).rev() {
+                btr.insert(i * 3, i);
+            }
+            for i in 0..500 {
+                assert_eq!(btr.get(&(i * 3)), Some(&i));
+                assert_eq!(btr.get(&(i * 3 + 1)), None);
+            }
+            assert_eq!(btr.get(&-1), None);
+            assert_eq!(btr.get(&1500), None);
+        }
+        let empty: BTree<i32, i32> = BTree::new(3);
+        assert_eq!(empty.get(&0), None);
+    }
+
+    #[test]
+    fn avl_lookup_without_allocation() {
+        let avl = AVL::from_iter((0..1000).map(|i| (i * 2, i)));
+        let before = allocations();
+        for i in 0..1000 {
+            assert_eq!(avl.get(&(i * 2)), Some(&i));
+            assert!(avl.contains(&(i * 2)));
+            assert!(!avl.contains(&(i * 2 + 1)));
+        }
+        assert_eq!(avl.get(&-1), None);
+        assert_eq!(allocations(), before);
+    }
+
+    #[test]
+    fn btree_contains_descends() {
+        let btr = BTree::from_iter((0..1000).map(|i| (i * 2, i)));
+        let before = allocations();
+        for i in 0..1000 {
+            assert!(btr.contains(&(i * 2)));
+            assert!(!btr.contains(&(i * 2 + 1)));
+        }
+        assert!(!btr.contains(&-1));
+        assert_eq!(allocations(), before);
+        let empty: BTree<i32, i32> = BTree::new(3);
+        assert!(!empty.contains(&0));
+    }
+
+    #[test]
+    fn avl_peek_min_max() {
+        let mut avl = AVL::new();
+        assert_eq!(avl.peek_min(), None);
+        assert_eq!(avl.peek_max(), None);
+        for i in [5, 3, 9, 1, 7] {
+            avl.insert(i, i * 10);
+        }
+        assert_eq!(avl.peek_min(), Some((&1, &10)));
+        assert_eq!(avl.peek_max(), Some((&9, &90)));
+        assert_eq!(avl.len(), 5);
+        avl.remove(&1);
+        avl.remove(&9);
+        assert_eq!(avl.peek_min(), Some((&3, &30)));
+        assert_eq!(avl.peek_max(), Some((&7, &70)));
+    }
+
+    #[test]
+    fn btree_first_last_key_value() {
+        let mut btr = BTree::new(3);
+        assert_eq!(btr.first_key_value(), None);
+        assert_eq!(btr.last_key_value(), None);
+        for i in (0..50).rev() {
+            btr.insert(i, i * 10);
+        }
+        assert_eq!(btr.first_key_value(), Some((&0, &0)));
+        assert_eq!(btr.last_key_value(), Some((&49, &490)));
+        assert_eq!(btr.len(), 50);
+        btr.pop_min();
+        btr.pop_max();
+        assert_eq!(btr.first_key_value(), Some((&1, &10)));
+        assert_eq!(btr.last_key_value(), Some((&48, &480)));
+    }
+
+    #[test]
+    fn avl_pop_min_max() {
+        let mut avl = AVL::from_iter((0..20).map(|i| (i, i * 10)));
+        assert_eq!(avl.pop_min(), Some((0, 0)));
+        assert_eq!(avl.pop_max(), Some((19, 190)));
+        assert_eq!(avl.len(), 18);
+        assert!(avl.is_balanced_tree());
+        let mut order = vec![];
+        while let Some((k, _)) = avl.pop_min() {
+            order.push(k);
+            if let Some((k, _)) = avl.pop_max() {
+                order.push(k);
+            }
+        }
+        assert_eq!(order.len(), 18);
+        assert_eq!(&order[..4], &[1, 18, 2, 17]);
+        assert!(avl.is_empty());
+        assert_eq!(avl.pop_min(), None);
+        assert_eq!(avl.pop_max(), None);
+    }
+
+    #[test]
+    fn btree_height() {
+        let mut small = BTree::new(3);
+        let mut large = BTree::new(16);
+        assert_eq!(small.height(), 0);
+        small.insert(0, 0);
+        assert_eq!(small.height(), 1);
+        for i in 1..1000 {
+            small.insert(i, i);
+            large.insert(i, i);
+        }
+        assert!(small.height() > large.height());
+        assert!(large.height() >= 2);
+    }
+
+    #[test]
+    fn avl_retain() {
+        let mut avl = AVL::from_iter((0..100).map(|i| (i, i)));
+        avl.retain(|k, v| {
+            *v *= 10;
+            k % 3 == 0
+        });
+        assert_eq!(avl.len(), 34);
+        assert!(avl.is_balanced_tree());
+        assert!(avl.iter().all(|(k, v)| k % 3 == 0 && *v == k * 10));
+        avl.retain(|_, _| true);
+        assert_eq!(avl.len(), 34);
+        avl.retain(|_, _| false);
+        assert!(avl.is_empty());
+        assert_eq!(avl.peek_root(), None);
+    }
+
+    #[test]
+    fn btree_retain() {
+        let mut btr = BTree::new(3);
+        for i in 0..100 {
+            btr.insert(i, i);
+        }
+        let height = btr.height();
+        btr.retain(|_, _| true);
+        assert_eq!(btr.len(), 100);
+        assert_eq!(btr.height(), height);
+
+        btr.retain(|k, v| {
+            *v += 1;
+            k % 2 == 1
+        });
+        assert_eq!(btr.len(), 50);
+        assert_eq!(btr.validate(), Ok(()));
+        assert!(btr.iter().all(|(k, v)| k % 2 == 1 && *v == k + 1));
+
+        btr.retain(|_, _| false);
+        assert_eq!(btr.len(), 0);
+        assert_eq!(btr.height(), 0);
+        assert_eq!(btr.iter().next(), None);
+    }
+
+    #[test]
+    fn avl_extend() {
+        let mut avl = AVL::from_iter([(1, 1), (2, 2)]);
+        avl.extend(vec![(3, 3), (2, 20)]);
+        assert_eq!(avl.len(), 3);
+        assert_eq!(avl.get(&2), Some(&20));
+        let other = AVL::from_iter([(10, 10), (11, 11)]);
+        avl.extend(other.iter());
+        assert_eq!(avl.len(), 5);
+        assert!(avl.is_balanced_tree());
+    }
+
+    #[test]
+    fn btree_extend() {
+        let mut btr = BTree::from_iter([(1, 1), (2, 2)]);
+        btr.extend(vec![(3, 3), (2, 20)]);
+        assert_eq!(btr.len(), 3);
+        assert_eq!(btr.get(&2), Some(&20));
+        let other = BTree::from_iter([(10, 10), (11, 11)]);
+        btr.extend(other.iter());
+        assert_eq!(btr.len(), 5);
+        assert_eq!(btr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn avl_append() {
+        let mut avl = AVL::from_iter((0..50).map(|i| (i * 2, 0)));
+        let mut other = AVL::from_iter((0..50).map(|i| (i * 3, 1)));
+        avl.append(&mut other);
+        assert_eq!(avl.len(), 50 + 50 - 17);
+        assert!(avl.is_balanced_tree());
+        assert_eq!(avl.get(&6), Some(&1));
+        assert_eq!(avl.get(&4), Some(&0));
+        assert!(other.is_empty());
+        assert_eq!(other.len(), 0);
+        assert_eq!(other.peek_root(), None);
+        let keys: Vec<_> = avl.keys().copied().collect();
+        assert!(keys.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn btree_append() {
+        let mut btr = BTree::new(4);
+        let mut other = BTree::new(4);
+        for i in 0..50 {
+            btr.insert(i * 2, 0);
+            other.insert(i * 2 + 1, 1);
+        }
+        other.insert(0, 1);
+        btr.append(&mut other);
+        assert_eq!(btr.len(), 100);
+        assert_eq!(btr.get(&0), Some(&1));
+        assert_eq!(btr.validate(), Ok(()));
+        let keys: Vec<_> = btr.keys().copied().collect();
+        assert_eq!(keys, (0..100).collect::<Vec<_>>());
+        assert_eq!(other.len(), 0);
+        assert_eq!(other.height(), 0);
+
+        let mut larger_order = BTree::from_iter([(200, 200)]);
+        btr.append(&mut larger_order);
+        assert_eq!(btr.len(), 101);
+        assert_eq!(btr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn avl_split_off() {
+        let mut avl = AVL::from_iter((0..100).map(|i| (i, i)));
+        let upper = avl.split_off(&40);
+        assert_eq!(avl.len(), 40);
+        assert_eq!(upper.len(), 60);
+        assert!(avl.is_balanced_tree());
+        assert!(upper.is_balanced_tree());
+        assert_eq!(avl.peek_max(), Some((&39, &39)));
+        assert_eq!(upper.peek_min(), Some((&40, &40)));
+
+        let all = avl.split_off(&-1);
+        assert!(avl.is_empty());
+        assert_eq!(all.len(), 40);
+        let mut all = all;
+        let none = all.split_off(&1000);
+        assert!(none.is_empty());
+        assert_eq!(all.len(), 40);
+    }
+
+    #[test]
+    fn btree_split_off() {
+        let mut btr = BTree::new(3);
+        for i in 0..100 {
+            btr.insert(i, i);
+        }
+        let upper = btr.split_off(&40);
+        assert_eq!(btr.len(), 40);
+        assert_eq!(upper.len(), 60);
+        assert_eq!(btr.validate(), Ok(()));
+        assert_eq!(upper.validate(), Ok(()));
+        assert_eq!(btr.last_key_value(), Some((&39, &39)));
+        assert_eq!(upper.first_key_value(), Some((&40, &40)));
+
+        let mut all = btr.split_off(&-1);
+        assert_eq!(btr.len(), 0);
+        assert_eq!(btr.height(), 0);
+        assert_eq!(all.len(), 40);
+        let none = all.split_off(&1000);
+        assert!(none.is_empty());
+        assert_eq!(all.len(), 40);
+        assert_eq!(all.validate(), Ok(()));
+    }
+
+    #[test]
+    fn avl_predecessor_successor() {
+        let avl = AVL::from_iter((0..50).map(|i| (i * 10, i)));
+        assert_eq!(avl.predecessor(&0), None);
+        assert_eq!(avl.successor(&490), None);
+        assert_eq!(avl.predecessor(&-5), None);
+        assert_eq!(avl.successor(&495), None);
+        for i in 1..49 {
+            let k = i * 10;
+            assert_eq!(avl.predecessor(&k), Some((&(k - 10), &(i - 1))));
+            assert_eq!(avl.successor(&k), Some((&(k + 10), &(i + 1))));
+            assert_eq!(avl.predecessor(&(k + 5)), Some((&k, &i)));
+            assert_eq!(avl.successor(&(k - 5)), Some((&k, &i)));
+        }
+        let empty: AVL<i32, i32> = AVL::new();
+        assert_eq!(empty.predecessor(&0), None);
+        assert_eq!(empty.successor(&0), None);
+    }
+
+    #[test]
+    fn btree_predecessor_successor() {
+        for order in [3, 5] {
+            let mut btr = BTree::new(order);
+            for i in 0..50 {
+                btr.insert(i * 10, i);
+            }
+            assert!(btr.height() > 1);
+            assert_eq!(btr.predecessor(&0), None);
+            assert_eq!(btr.successor(&490), None);
+            assert_eq!(btr.predecessor(&-5), None);
+            assert_eq!(btr.successor(&495), None);
+            for i in 1..49 {
+                let k = i * 10;
+                assert_eq!(btr.predecessor(&k), Some((&(k - 10), &(i - 1))));
+                assert_eq!(btr.successor(&k), Some((&(k + 10), &(i + 1))));
+                assert_eq!(btr.predecessor(&(k + 5)), Some((&k, &i)));
+                assert_eq!(btr.successor(&(k - 5)), Some((&k, &i)));
+            }
+        }
+    }
+
+    #[test]
+    fn avl_floor_ceiling() {
+        let avl = AVL::from_iter((0..50).map(|i| (i * 10, i)));
+        assert_eq!(avl.floor(&-1), None);
+        assert_eq!(avl.ceiling(&491), None);
+        assert_eq!(avl.floor(&1000), Some((&490, &49)));
+        assert_eq!(avl.ceiling(&-1000), Some((&0, &0)));
+        for i in 0..50 {
+            let k = i * 10;
+            assert_eq!(avl.floor(&k), Some((&k, &i)));
+            assert_eq!(avl.ceiling(&k), Some((&k, &i)));
+            assert_eq!(avl.floor(&(k + 9)), Some((&k, &i)));
+            assert_eq!(avl.ceiling(&(k - 9)), Some((&k, &i)));
+        }
+    }
+
+    #[test]
+    fn btree_floor_ceiling() {
+        let mut btr = BTree::new(3);
+        for i in 0..50 {
+            btr.insert(i * 10, i);
+        }
+        assert_eq!(btr.floor(&-1), None);
+        assert_eq!(btr.ceiling(&491), None);
+        assert_eq!(btr.floor(&1000), Some((&490, &49)));
+        assert_eq!(btr.ceiling(&-1000), Some((&0, &0)));
+        for i in 0..50 {
+            let k = i * 10;
+            assert_eq!(btr.floor(&k), Some((&k, &i)));
+            assert_eq!(btr.ceiling(&k), Some((&k, &i)));
+            assert_eq!(btr.floor(&(k + 9)), Some((&k, &i)));
+            assert_eq!(btr.ceiling(&(k - 9)), Some((&k, &i)));
+        }
+    }
+
+    #[test]
+    fn avl_select_rank() {
+        let mut avl = AVL::new();
+        for i in 0..200 {
+            avl.insert((i * 37) % 200, i);
+        }
+        for i in (0..200).step_by(3) {
+            avl.remove(&i);
+        }
+        avl.pop_min();
+        avl.pop_max();
+        let upper = avl.split_off(&150);
+        let expected: Vec<_> = avl.keys().copied().collect();
+        assert_eq!(avl.len(), expected.len());
+        for (n, k) in expected.iter().enumerate() {
+            assert_eq!(avl.select(n).map(|(k, _)| *k), Some(*k));
+            assert_eq!(avl.rank(k), Some(n));
+        }
+        for absent in (-1..160).filter(|k| !avl.contains(k)) {
+            let less = expected.iter().filter(|k| **k < absent).count();
+            assert_eq!(avl.rank(&absent), Some(less));
+        }
+        assert_eq!(avl.select(expected.len()), None);
+        assert_eq!(avl.rank(&0), Some(0));
+        assert_eq!(upper.select(0).map(|(k, _)| *k), Some(151));
+        assert_eq!(upper.rank(&151), Some(0));
+        assert_eq!(upper.rank(&1000), Some(upper.len()));
+    }
+
+    #[test]
+    fn btree_select_rank() {
+        for order in [3, 4, 7] {
+            let mut btr = BTree::new(order);
+            for i in 0..300 {
+                btr.insert((i * 37) % 300, i);
+            }
+            for i in (0..300).step_by(3) {
+                btr.remove(&i);
+            }
+            btr.pop_min();
+            btr.pop_max();
+            let upper = btr.split_off(&250);
+            let expected: Vec<_> = btr.keys().copied().collect();
+            assert_eq!(btr.len(), expected.len());
+            for (n, k) in expected.iter().enumerate() {
+                assert_eq!(btr.select(n).map(|(k, _)| *k), Some(*k));
+                assert_eq!(btr.rank(k), Some(n));
+            }
+            for absent in (-1..260).filter(|k| !btr.contains(k)) {
+                let less = expected.iter().filter(|k| **k < absent).count();
+                assert_eq!(btr.rank(&absent), Some(less));
+            }
+            assert_eq!(btr.select(expected.len()), None);
+            assert_eq!(btr.rank(&0), Some(0));
+            assert_eq!(upper.select(0).map(|(k, _)| *k), Some(250));
+            assert_eq!(upper.rank(&250), Some(0));
+            assert_eq!(upper.rank(&1000), Some(upper.len()));
+        }
+        // the median of a percentile index
+        let mut sorted = BTree::new(5);
+        sorted.append_sorted_run((0..1001).map(|i| (i, i)));
+        assert_eq!(sorted.select(sorted.len() / 2), Some((&500, &500)));
+    }
+
+    #[test]
+    fn avl_get_key_value() {
+        use std::cmp::Ordering;
+        #[derive(Debug, PartialEq, Eq)]
+        struct Keyed(u32, &'static str);
+        impl PartialOrd for Keyed {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Keyed {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+        let mut avl = AVL::new();
+        avl.insert(Keyed(1, "one"), 1);
+        avl.insert(Keyed(2, "two"), 2);
+        let (k, v) = avl.get_key_value(&Keyed(2, "")).unwrap();
+        assert_eq!(k.1, "two");
+        assert_eq!(*v, 2);
+        assert_eq!(avl.get_key_value(&Keyed(3, "")), None);
+    }
+
+    #[test]
+    fn btree_get_key_value() {
+        use std::cmp::Ordering;
+        #[derive(Debug)]
+        struct Keyed(u32, String);
+        impl PartialEq for Keyed {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+        impl Eq for Keyed {}
+        impl PartialOrd for Keyed {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+        impl Ord for Keyed {
+            fn cmp(&self, other: &Self) -> Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+        let mut btr = BTree::new(3);
+        for i in 0..20 {
+            btr.insert(Keyed(i, i.to_string()), i);
+        }
+        for i in 0..20 {
+            let (k, v) = btr.get_key_value(&Keyed(i, String::new())).unwrap();
+            assert_eq!(k.1, i.to_string());
+            assert_eq!(*v, i);
+        }
+        assert!(btr.get_key_value(&Keyed(20, String::new())).is_none());
+    }
+
+    #[test]
+    fn avl_remove_entry() {
+        let mut avl = AVL::new();
+        for i in 0..50 {
+            avl.insert(i.to_string(), i);
+        }
+        let key = String::from("25");
+        assert_eq!(avl.remove_entry(&key), Some((key.clone(), 25)));
+        assert_eq!(avl.remove_entry(&key), None);
+        assert_eq!(avl.len(), 49);
+        assert!(avl.is_balanced_tree());
+    }
+
+    #[test]
+    fn btree_remove_entry() {
+        let mut btr = BTree::new(3);
+        for i in 0..50 {
+            btr.insert(i.to_string(), i);
+        }
+        let key = String::from("25");
+        assert_eq!(btr.remove_entry(&key), Some((key.clone(), 25)));
+        assert_eq!(btr.remove_entry(&key), None);
+        assert_eq!(btr.remove(&String::from("26")), Some(26));
+        assert_eq!(btr.len(), 48);
+        assert_eq!(btr.validate(), Ok(()));
+    }
+
+    #[test]
+    fn avl_drain() {
+        let mut avl = AVL::from_iter((0..5).map(|i| (i, i.to_string())));
+        let all: Vec<_> = avl.drain().map(|(k, _)| k).collect();
+        assert_eq!(all, vec![0, 1, 2, 3, 4]);
+        assert!(avl.is_empty());
+
+        avl.extend((0..5).map(|i| (i, i.to_string())));
+        let mut drain = avl.drain();
+        assert_eq!(drain.next(), Some((0, String::from("0"))));
+        assert_eq!(drain.next_back(), Some((4, String::from("4"))));
+        assert_eq!(drain.next(), Some((1, String::from("1"))));
+        drop(drain);
+        assert_eq!(avl.len(), 0);
+        assert_eq!(avl.peek_root(), None);
+
+        avl.insert(7, String::from("7"));
+        assert_eq!(avl.len(), 1);
+    }
+
+    #[test]
+    fn btree_drain() {
+        let mut btr = BTree::new(3);
+        for i in 0..5 {
+            btr.insert(i, i.to_string());
+        }
+        let mut drain = btr.drain();
+        assert_eq!(drain.next(), Some((0, String::from("0"))));
+        assert_eq!(drain.next_back(), Some((4, String::from("4"))));
+        assert_eq!(drain.next(), Some((1, String::from("1"))));
+        drop(drain);
+        assert_eq!(btr.len(), 0);
+        assert_eq!(btr.height(), 0);
+
+        btr.extend((10..20).map(|i| (i, i.to_string())));
+        assert_eq!(btr.len(), 10);
+        let all: Vec<_> = btr.drain().map(|(k, _)| k).collect();
+        assert_eq!(all, (10..20).collect::<Vec<_>>());
+        assert!(btr.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn avl_index() {
+        let avl = AVL::from_iter((0..10).map(|i| (i, i * 10)));
+        assert_eq!(avl[&3], 30);
+        let _ = avl[&10];
+    }
+
+    #[test]
+    #[should_panic(expected = "key not found")]
+    fn btree_index() {
+        let btr = BTree::from_iter((0..10).map(|i| (i, i * 10)));
+        assert_eq!(btr[&3], 30);
+        let _ = btr[&10];
+    }
+
+    #[test]
+    fn btree_borrow_lookup() {
+        let mut b: BTree<String, usize> = BTree::new(4);
+        let words = ["pear", "apple", "fig", "kiwi", "plum", "date", "lime"];
+        for (i, w) in words.iter().enumerate() {
+            b.insert(w.to_string(), i);
+        }
+        assert_eq!(b.get("fig"), Some(&2));
+        assert_eq!(b.get_key_value("kiwi"), Some((&"kiwi".to_string(), &3)));
+        assert!(b.contains("lime"));
+        assert!(!b.contains("grape"));
+        if let Some(v) = b.get_mut("date") {
+            *v += 10;
+        }
+        assert_eq!(b["date"], 15);
+        assert_eq!(b.remove("apple"), Some(1));
+        assert_eq!(b.remove_entry("plum"), Some(("plum".to_string(), 4)));
+        assert_eq!(b.remove("apple"), None);
+        assert_eq!(b.len(), 5);
+        assert!(b.validate().is_ok());
+    }
+
+    #[test]
+    fn avl_into_iter_refs() {
+        let mut avl = AVL::from_iter((0..20).map(|i| (i, i)));
+        for (_, v) in &mut avl {
+            *v *= 2;
+        }
+        let mut seen = Vec::new();
+        for (k, v) in &avl {
+            seen.push((*k, *v));
+        }
+        assert_eq!(seen, (0..20).map(|i| (i, i * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn btree_into_iter_refs() {
+        let mut btr = BTree::from_iter((0..50).map(|i| (i, i)));
+        for (_, v) in &mut btr {
+            *v *= 2;
+        }
+        let mut seen = Vec::new();
+        for (k, v) in &btr {
+            seen.push((*k, *v));
+        }
+        assert_eq!(seen, (0..50).map(|i| (i, i * 2)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn avl_sequential_insert_stays_balanced() {
+        let n = 100_000;
+        let mut avl = AVL::new();
+        for i in 0..n {
+            avl.insert(i, i);
+        }
+        assert_eq!(avl.len(), n);
+        assert!(avl.is_balanced_tree());
+        assert_eq!(avl.select(n / 2), Some((&(n / 2), &(n / 2))));
+        for i in (0..n).rev().step_by(7) {
+            avl.insert(i, 0);
+        }
+        assert_eq!(avl.len(), n);
+        assert!(avl.is_balanced_tree());
+    }
+
+    #[test]
+    fn avl_bulk_remove_stays_balanced() {
+        let n: usize = 100_000;
+        let mut avl = AVL::new();
+        for i in 0..n {
+            avl.insert(i, i);
+        }
+        // 7919 is coprime with n so this visits every key once
+        for (count, i) in (0..n).map(|i| (i * 7919) % n).enumerate() {
+            assert_eq!(avl.remove(&i), Some(i));
+            if count % 10_000 == 0 {
+                assert!(avl.is_balanced_tree());
+                assert_eq!(avl.len(), n - count - 1);
+            }
+        }
+        assert!(avl.is_empty());
+    }
+
+    #[test]
+    fn btree_iter_memory_follows_height() {
+        for order in [3, 4, 7] {
+            let n = 20_000;
+            let mut btr = BTree::new(order);
+            for i in 0..n {
+                btr.insert(i, i);
+            }
+            let before = allocations();
+            let mut expected = 0;
+            for (k, _) in btr.iter() {
+                assert_eq!(*k, expected);
+                expected += 1;
+            }
+            assert_eq!(expected, n);
+            for (k, _) in btr.iter().rev() {
+                expected -= 1;
+                assert_eq!(*k, expected);
+            }
+            // only the two traversal stacks are allocated
+            assert!(allocations() - before <= 4 * btr.height());
+        }
+    }
+
+    #[test]
+    fn btree_iter_mixed_ends() {
+        let btr = BTree::from_iter((1..=5).map(|i| (i, i * 10)));
+        let mut iter = btr.iter();
+        let mut seen = Vec::new();
+        let mut forward = true;
+        loop {
+            let item = if forward {
+                iter.next()
+            } else {
+                iter.next_back()
+            };
+            match item {
+                None => break,
+                Some((k, v)) => seen.push((*k, *v)),
+            }
+            forward = !forward;
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(seen, vec![(1, 10), (5, 50), (2, 20), (4, 40), (3, 30)]);
+    }
+
+    #[test]
+    fn avl_iter_mixed_ends() {
+        // a lopsided tree whose right subtree is deeper than the left one
+        let mut avl = AVL::new();
+        for k in [2, 1, 3, 4] {
+            avl.insert(k, k * 10);
+        }
+        assert_eq!(avl.peek_root(), Some((&2, &20)));
+        let mut iter = avl.iter();
+        let mut seen = Vec::new();
+        let mut forward = true;
+        loop {
+            let item = if forward {
+                iter.next()
+            } else {
+                iter.next_back()
+            };
+            match item {
+                None => break,
+                Some((k, v)) => seen.push((*k, *v)),
+            }
+            forward = !forward;
+        }
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+        assert_eq!(seen, vec![(1, 10), (4, 40), (2, 20), (3, 30)]);
+        let mut keys: Vec<_> = seen.iter().map(|(k, _)| *k).collect();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), seen.len());
+    }
+
+    #[test]
+    fn btree_with_order_from_iter() {
+        for order in [3, 4, 64] {
+            let btr = BTree::with_order_from_iter(order, (0..500).rev().map(|i| (i, i)));
+            assert_eq!(btr.len(), 500);
+            assert!(btr.validate().is_ok());
+            assert!(btr.iter().map(|(k, _)| *k).eq(0..500));
+        }
+        let narrow = BTree::with_order_from_iter(3, (0..500).map(|i| (i, i)));
+        let wide = BTree::with_order_from_iter(64, (0..500).map(|i| (i, i)));
+        assert!(narrow.height() > wide.height());
+        let empty = BTree::<i32, i32>::with_order_from_iter(4, Vec::new());
+        assert!(empty.is_empty());
+    }
+
+    #[test]
+    fn btree_order_accessors() {
+        let expected = [(3, 1), (4, 2), (5, 2), (6, 3), (64, 32)];
+        for (order, min) in expected {
+            let mut btr = BTree::with_order_from_iter(order, (0..100).map(|i| (i, i)));
+            assert_eq!(btr.order(), order);
+            assert_eq!(btr.max_keys(), order);
+            assert_eq!(btr.min_keys(), min);
+            for i in (0..100).step_by(4) {
+                btr.remove(&i);
+            }
+            let nodes = btr.node_keys();
+            assert!(nodes.iter().all(|n| n.len() <= btr.max_keys()));
+            assert!(nodes[1..].iter().all(|n| n.len() >= order.div_ceil(2) - 1));
+            let upper = btr.split_off(&50);
+            assert_eq!(upper.order(), order);
+            btr.append(&mut BTree::new(3));
+            assert_eq!(btr.order(), order);
+        }
+    }
+
+    #[test]
+    fn avl_debug() {
+        let avl = AVL::from_iter([(3, "c"), (1, "a"), (2, "b")]);
+        assert_eq!(format!("{:?}", avl), r#"{1: "a", 2: "b", 3: "c"}"#);
+        assert_eq!(format!("{:?}", AVL::<i32, i32>::new()), "{}");
+    }
+
+    #[test]
+    fn btree_debug() {
+        let btr = BTree::from_iter([(3, "c"), (1, "a"), (2, "b")]);
+        assert_eq!(format!("{:?}", btr), r#"{1: "a", 2: "b", 3: "c"}"#);
+        assert_eq!(format!("{:?}", BTree::<i32, i32>::new(4)), "{}");
+
+        let btr = BTree::with_order_from_iter(3, (1..=4).map(|i| (i, i)));
+        let expected = "BTree {
+    order: 3,
+    len: 4,
+    root: Node {
+        keys: [
+            2,
+        ],
+        children: [
+            Node {
+                keys: [
+                    1,
+                ],
+            },
+            Node {
+                keys: [
+                    3,
+                    4,
+                ],
+            },
+        ],
+    },
+}";
+        assert_eq!(format!("{:#?}", btr), expected);
+    }
+
+    #[test]
+    fn avl_eq() {
+        let a = AVL::from_iter((0..100).map(|i| (i, i * 2)));
+        let b = AVL::from_iter((0..100).rev().map(|i| (i, i * 2)));
+        assert_eq!(a, b);
+        let mut c = AVL::from_iter((0..100).map(|i| (i, i * 2)));
+        c.insert(50, 0);
+        assert_ne!(a, c);
+        c.remove(&50);
+        assert_ne!(a, c);
+        assert_eq!(AVL::<i32, i32>::new(), AVL::new());
+    }
+
+    #[test]
+    fn btree_eq() {
+        let a = BTree::with_order_from_iter(3, (0..100).map(|i| (i, i * 2)));
+        let b = BTree::with_order_from_iter(16, (0..100).rev().map(|i| (i, i * 2)));
+        assert_eq!(a, b);
+        let mut c = BTree::with_order_from_iter(4, (0..100).map(|i| (i, i * 2)));
+        c.insert(50, 0);
+        assert_ne!(a, c);
+        c.remove(&50);
+        assert_ne!(a, c);
+        assert_eq!(BTree::<i32, i32>::new(3), BTree::new(5));
+    }
+
+    #[test]
+    fn avl_btree_hash() {
+        use std::collections::hash_map::DefaultHasher;
+        use std::collections::HashSet;
+        use std::hash::{Hash, Hasher};
+        fn hash_of<T: Hash>(t: &T) -> u64 {
+            let mut h = DefaultHasher::new();
+            t.hash(&mut h);
+            h.finish()
+        }
+        let a = AVL::from_iter((0..50).map(|i| (i, i)));
+        let b = AVL::from_iter((0..50).rev().map(|i| (i, i)));
+        assert_eq!(hash_of(&a), hash_of(&b));
+        let c = BTree::with_order_from_iter(3, (0..50).map(|i| (i, i)));
+        let d = BTree::with_order_from_iter(9, (0..50).rev().map(|i| (i, i)));
+        assert_eq!(hash_of(&c), hash_of(&d));
+
+        let mut set = HashSet::new();
+        assert!(set.insert(c));
+        assert!(!set.insert(d));
+        assert!(set.insert(BTree::from_iter((0..49).map(|i| (i, i)))));
+    }
+
+    #[test]
+    fn avl_clone_non_copy() {
+        let a = AVL::from_iter((0..30).map(|i| (i.to_string(), vec![i as u8; 3])));
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        b.insert("0".to_string(), Vec::new());
+        assert_eq!(a.get(&"0".to_string()), Some(&vec![0u8; 3]));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn btree_clone_non_copy() {
+        let a = BTree::with_order_from_iter(3, (0..30).map(|i| (i.to_string(), vec![i as u8; 3])));
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        assert_eq!(b.order(), 3);
+        b.insert("0".to_string(), Vec::new());
+        assert_eq!(a.get("0"), Some(&vec![0u8; 3]));
+        assert_ne!(a, b);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn avl_serde_round_trip() {
+        let avl = AVL::from_iter((0..20).rev().map(|i| (i.to_string(), i)));
+        let json = serde_json::to_string(&avl).unwrap();
+        assert!(json.starts_with(r#"{"0":0,"1":1,"10":10"#));
+        let back: AVL<String, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, avl);
+        assert!(serde_json::from_str::<AVL<String, i32>>("[1, 2]").is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn btree_serde_round_trip() {
+        let btr = BTree::with_order_from_iter(3, (0..20).rev().map(|i| (i.to_string(), i)));
+        let json = serde_json::to_string(&btr).unwrap();
+        assert!(json.starts_with(r#"{"0":0,"1":1,"10":10"#));
+        let back: BTree<String, i32> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, btr);
+        assert_eq!(back.order(), 5);
+        assert!(back.validate().is_ok());
+        assert!(serde_json::from_str::<BTree<String, i32>>("[1, 2]").is_err());
+    }
+
+    #[test]
+    fn avl_default() {
+        #[derive(Default)]
+        struct Index {
+            names: AVL<String, usize>,
+        }
+        let mut idx = Index::default();
+        assert!(idx.names.is_empty());
+        idx.names.insert("a".to_string(), 1);
+        let taken = std::mem::take(&mut idx.names);
+        assert_eq!(taken.len(), 1);
+        assert!(idx.names.is_empty());
+    }
+
+    #[test]
+    fn avl_btree_into_sorted_vec() {
+        let expected: Vec<(i32, String)> = (0..200).map(|i| (i, i.to_string())).collect();
+        let avl = AVL::from_iter(expected.iter().rev().cloned());
+        assert_eq!(avl.into_sorted_vec(), expected);
+        let btr = BTree::with_order_from_iter(3, expected.iter().rev().cloned());
+        let out = btr.into_sorted_vec();
+        assert_eq!(out, expected);
+        assert_eq!(out.capacity(), out.len());
+        assert!(AVL::<i32, i32>::new().into_sorted_vec().is_empty());
+        assert!(BTree::<i32, i32>::new(4).into_sorted_vec().is_empty());
+    }
+
+    #[test]
+    fn avl_from_sorted() {
+        for n in [0usize, 1, 2, 3, 10, 1000] {
+            let pairs: Vec<(usize, usize)> = (0..n).map(|i| (i, i * 2)).collect();
+            let avl = AVL::from_sorted(pairs.clone());
+            assert_eq!(avl.len(), n);
+            assert!(avl.is_balanced_tree());
+            assert_eq!(avl, AVL::from_iter(pairs));
+            if n > 0 {
+                assert_eq!(avl.select(n - 1), Some((&(n - 1), &((n - 1) * 2))));
+                assert_eq!(avl.rank(&(n / 3)), Some(n / 3));
+            }
+        }
+        let mut avl = AVL::from_sorted((0..100).map(|i| (i, i)).collect());
+        for i in (0..100).step_by(3) {
+            assert_eq!(avl.remove(&i), Some(i));
+        }
+        avl.insert(1000, 0);
+        assert!(avl.is_balanced_tree());
+        assert_eq!(avl.len(), 67);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "sorted")]
+    fn avl_from_sorted_unsorted() {
+        AVL::from_sorted(vec![(2, 2), (1, 1)]);
+    }
+
+    #[test]
+    fn btree_from_sorted() {
+        for order in [3, 4, 5, 8, 33] {
+            for n in (0..200).chain([1000, 4097]) {
+                let btr = BTree::from_sorted(order, (0..n).map(|i| (i, i * 2)));
+                assert_eq!(btr.len(), n);
+                assert_eq!(btr.validate(), Ok(()));
+                assert!(btr.iter().map(|(k, _)| *k).eq(0..n));
+                if n > 0 {
+                    assert_eq!(btr.select(n / 2), Some((&(n / 2), &(n / 2 * 2))));
+                }
+            }
+        }
+        let mut btr = BTree::from_sorted(4, (0..300).map(|i| (i, i)));
+        for i in (0..300).step_by(3) {
+            assert_eq!(btr.remove(&i), Some(i));
+        }
+        btr.insert(1000, 0);
+        assert_eq!(btr.validate(), Ok(()));
+        assert_eq!(btr.len(), 201);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic(expected = "sorted")]
+    fn btree_from_sorted_unsorted() {
+        BTree::from_sorted(4, vec![(1, 1), (1, 1)]);
+    }
+
+    #[test]
+    fn avl_set_operations() {
+        let a = AVL::from_iter((0..30).filter(|i| i % 2 == 0).map(|i| (i, "a")));
+        let b = AVL::from_iter((0..30).filter(|i| i % 3 == 0).map(|i| (i, "b")));
+
+        let u = a.union(&b);
+        assert!(u.is_balanced_tree());
+        let expected: Vec<_> = (0..30)
+            .filter(|i| i % 2 == 0 || i % 3 == 0)
+            .map(|i| (i, if i % 3 == 0 { "b" } else { "a" }))
+            .collect();
+        assert_eq!(u.into_sorted_vec(), expected);
+
+        let i = a.intersection(&b);
+        let expected: Vec<_> = (0..30).filter(|i| i % 6 == 0).map(|i| (i, "a")).collect();
+        assert_eq!(i.into_sorted_vec(), expected);
+
+        let d = a.difference(&b);
+        let expected: Vec<_> = (0..30)
+            .filter(|i| i % 2 == 0 && i % 3 != 0)
+            .map(|i| (i, "a"))
+            .collect();
+        assert_eq!(d.into_sorted_vec(), expected);
+
+        let empty = AVL::new();
+        assert_eq!(a.union(&empty), a);
+        assert!(a.intersection(&empty).is_empty());
+        assert_eq!(a.difference(&empty), a);
+        assert_eq!(empty.union(&a), a);
+    }
+
+    #[test]
+    fn btree_set_operations() {
+        let a = BTree::with_order_from_iter(3, (0..300).filter(|i| i % 2 == 0).map(|i| (i, "a")));
+        let b = BTree::with_order_from_iter(7, (0..300).filter(|i| i % 3 == 0).map(|i| (i, "b")));
+
+        let u = a.union(&b, 4);
+        assert_eq!(u.order(), 4);
+        assert_eq!(u.validate(), Ok(()));
+        let expected: Vec<_> = (0..300)
+            .filter(|i| i % 2 == 0 || i % 3 == 0)
+            .map(|i| (i, if i % 3 == 0 { "b" } else { "a" }))
+            .collect();
+        assert_eq!(u.into_sorted_vec(), expected);
+
+        let i = a.intersection(&b, 5);
+        assert_eq!(i.validate(), Ok(()));
+        let expected: Vec<_> = (0..300).filter(|i| i % 6 == 0).map(|i| (i, "a")).collect();
+        assert_eq!(i.into_sorted_vec(), expected);
+
+        let d = a.difference(&b, 3);
+        assert_eq!(d.validate(), Ok(()));
+        let expected: Vec<_> = (0..300)
+            .filter(|i| i % 2 == 0 && i % 3 != 0)
+            .map(|i| (i, "a"))
+            .collect();
+        assert_eq!(d.into_sorted_vec(), expected);
+
+        let empty = BTree::new(4);
+        assert_eq!(a.union(&empty, 4), a);
+        assert!(a.intersection(&empty, 4).is_empty());
+        assert_eq!(a.difference(&empty, 4), a);
+    }
+
+    #[test]
+    fn avl_btree_exact_size() {
+        let mut avl = AVL::from_iter((0..10).map(|i| (i, i)));
+        let mut iter = avl.iter();
+        assert_eq!(iter.len(), 10);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 8);
+        assert_eq!(iter.by_ref().count(), 8);
+        assert_eq!(iter.len(), 0);
+        assert_eq!(avl.iter_mut().len(), 10);
+        let mut into = avl.into_iter();
+        into.next_back();
+        assert_eq!(into.len(), 9);
+
+        let mut btr = BTree::with_order_from_iter(3, (0..10).map(|i| (i, i)));
+        let mut iter = btr.iter();
+        assert_eq!(iter.len(), 10);
+        iter.next();
+        iter.next_back();
+        assert_eq!(iter.len(), 8);
+        assert_eq!(iter.by_ref().count(), 8);
+        assert_eq!(iter.len(), 0);
+        assert_eq!(btr.iter_mut().len(), 10);
+        let mut into = btr.into_iter();
+        into.next();
+        assert_eq!(into.len(), 9);
+    }
+
+    #[test]
+    fn avl_btree_fused() {
+        fn assert_fused<I: std::iter::FusedIterator>(mut iter: I) {
+            while iter.next().is_some() {}
+            for _ in 0..3 {
+                assert!(iter.next().is_none());
+            }
+        }
+        let avl = AVL::from_iter((0..10).map(|i| (i, i)));
+        assert_fused(avl.iter());
+        let btr = BTree::from_iter((0..10).map(|i| (i, i)));
+        assert_fused(btr.iter());
+
+        let mut iter = btr.iter();
+        assert_eq!(iter.by_ref().rev().count(), 10);
+        assert_eq!(iter.next(), None);
+        assert_eq!(iter.next_back(), None);
+
+        assert_fused(avl.into_iter());
+        assert_fused(btr.into_iter());
+    }
+
+    #[test]
+    fn avl_first_last_key() {
+        let mut avl = AVL::from_iter([(5, vec![0u8; 64]), (1, vec![]), (9, vec![])]);
+        assert_eq!(avl.first_key(), Some(&1));
+        assert_eq!(avl.last_key(), Some(&9));
+        avl.remove(&9);
+        avl.remove(&1);
+        assert_eq!(avl.first_key(), avl.last_key());
+        avl.remove(&5);
+        assert_eq!(avl.first_key(), None);
+        assert_eq!(avl.last_key(), None);
+    }
+
+    #[test]
+    fn btree_pop_first_last() {
+        let mut btr = BTree::with_order_from_iter(3, (0..50).map(|i| (i, i)));
+        let mut std = std::collections::BTreeMap::from_iter((0..50).map(|i| (i, i)));
+        while !std.is_empty() {
+            assert_eq!(btr.pop_first(), std.pop_first());
+            assert_eq!(btr.pop_last(), std.pop_last());
+        }
+        assert_eq!(btr.pop_first(), None);
+        assert_eq!(btr.pop_last(), None);
+        assert!(btr.is_empty());
+    }
+
+    #[test]
+    fn avl_pop_first_last() {
+        let mut avl = AVL::from_iter((0..50).map(|i| (i, i)));
+        let mut std = std::collections::BTreeMap::from_iter((0..50).map(|i| (i, i)));
+        while !std.is_empty() {
+            assert_eq!(avl.pop_first(), std.pop_first());
+            assert_eq!(avl.pop_last(), std.pop_last());
+        }
+        assert_eq!(avl.pop_first(), None);
+        assert_eq!(avl.pop_last(), None);
+        assert!(avl.is_empty());
+    }
+
+    #[test]
+    fn avl_update() {
+        let mut avl = AVL::from_iter((0..20).map(|i| (i, vec![i])));
+        assert!(avl.update(&7, |v| v.push(70)));
+        assert_eq!(avl.get(&7), Some(&vec![7, 70]));
+        let mut called = false;
+        assert!(!avl.update(&20, |_| called = true));
+        assert!(!called);
+        assert_eq!(avl.len(), 20);
+    }
+
+    #[test]
+    fn btree_get_or_insert_with() {
+        let mut btr: BTree<i32, Vec<i32>> = BTree::new(3);
+        for i in (0..200).rev() {
+            btr.get_or_insert_with(i % 50, Vec::new).push(i);
+        }
+        assert_eq!(btr.len(), 50);
+        assert_eq!(btr.validate(), Ok(()));
+        assert_eq!(btr.get(&7), Some(&vec![157, 107, 57, 7]));
+
+        let mut called = false;
+        btr.get_or_insert_with(7, || {
+            called = true;
+            Vec::new()
+        });
+        assert!(!called);
+
+        let mut btr: BTree<i32, i32> = BTree::new(4);
+        for i in 0..300 {
+            assert_eq!(*btr.get_or_insert_with(i, || i * 2), i * 2);
+            assert_eq!(btr.validate(), Ok(()));
+        }
+
+        // the new key may be moved up by the splits
+        for order in [3, 4, 5, 6] {
+            let mut btr: BTree<i32, i32> = BTree::new(order);
+            for i in 0..500 {
+                let k = (i * 137) % 500;
+                *btr.get_or_insert_with(k, || 0) += k;
+                assert_eq!(btr.get(&k), Some(&k));
+            }
+            assert_eq!(btr.validate(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn avl_get_or_insert_with() {
+        let mut avl: AVL<i32, Vec<i32>> = AVL::new();
+        for i in (0..200).rev() {
+            avl.get_or_insert_with(i % 50, Vec::new).push(i);
+        }
+        assert_eq!(avl.len(), 50);
+        assert!(avl.is_balanced_tree());
+        assert_eq!(avl.get(&7), Some(&vec![157, 107, 57, 7]));
+
+        let mut called = false;
+        avl.get_or_insert_with(7, || {
+            called = true;
+            Vec::new()
+        });
+        assert!(!called);
+        assert_eq!(avl.len(), 50);
+    }
+
+    #[test]
+    fn avl_btree_count_range() {
+        use std::ops::Bound::{self, Excluded, Included, Unbounded};
+        use std::ops::RangeBounds;
+        let keys: Vec<i32> = (0..120).map(|i| i * 3).collect();
+        let avl = AVL::from_iter(keys.iter().map(|k| (*k, ())));
+        let btr = BTree::with_order_from_iter(4, keys.iter().map(|k| (*k, ())));
+        let bounds = |k: i32| [Included(k), Excluded(k), Unbounded];
+        for a in (-5..370).step_by(7) {
+            for b in (-5..370).step_by(11) {
+                for start in bounds(a) {
+                    for end in bounds(b) {
+                        let range: (Bound<i32>, Bound<i32>) = (start, end);
+                        let expected = keys.iter().filter(|k| range.contains(k)).count();
+                        assert_eq!(avl.count_range(range), expected);
+                        assert_eq!(btr.count_range(range), expected);
+                    }
+                }
+            }
+        }
+        assert_eq!(AVL::<i32, ()>::new().count_range(..), 0);
+        assert_eq!(BTree::<i32, ()>::new(3).count_range(..), 0);
+    }
 }
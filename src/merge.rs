@@ -0,0 +1,63 @@
+//! Merging two iterators of key-value pairs sorted by key in linear time
+//! which is shared by the set operations of the trees
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+/// Which pairs are kept by `merge_sorted`
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SetOp {
+    /// Keys in either side, the value of the right side wins
+    Union,
+    /// Keys in both sides with the value of the left side
+    Intersection,
+    /// Keys only in the left side
+    Difference,
+}
+
+/// Walking both sorted iterators once and cloning
+/// the kept pairs out in ascending order
+pub(crate) fn merge_sorted<'a, K, V, L, R>(left: L, right: R, op: SetOp) -> Vec<(K, V)>
+where
+    K: Ord + Clone + 'a,
+    V: Clone + 'a,
+    L: Iterator<Item = (&'a K, &'a V)>,
+    R: Iterator<Item = (&'a K, &'a V)>,
+{
+    let mut left = left.peekable();
+    let mut right = right.peekable();
+    let mut out = Vec::new();
+    loop {
+        let ordering = match (left.peek(), right.peek()) {
+            (None, None) => break,
+            (None, Some(_)) if op != SetOp::Union => break,
+            (Some(_), None) if op == SetOp::Intersection => break,
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (Some(l), Some(r)) => l.0.cmp(r.0),
+        };
+        match ordering {
+            Ordering::Less => {
+                let (k, v) = left.next().unwrap();
+                if op != SetOp::Intersection {
+                    out.push((k.clone(), v.clone()));
+                }
+            }
+            Ordering::Greater => {
+                let (k, v) = right.next().unwrap();
+                if op == SetOp::Union {
+                    out.push((k.clone(), v.clone()));
+                }
+            }
+            Ordering::Equal => {
+                let (k, l_v) = left.next().unwrap();
+                let (_, r_v) = right.next().unwrap();
+                match op {
+                    SetOp::Union => out.push((k.clone(), r_v.clone())),
+                    SetOp::Intersection => out.push((k.clone(), l_v.clone())),
+                    SetOp::Difference => {}
+                }
+            }
+        }
+    }
+    out
+}
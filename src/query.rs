@@ -0,0 +1,51 @@
+//! Queries answered from the sorted entries or the rank of a key
+//! which are shared by both trees
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Bound, RangeBounds};
+
+/// Counting the keys within a range in O(log n) time, `count_less`
+/// gives the number of keys less than a key and whether it is present
+pub(crate) fn count_range<K, R, F>(range: R, len: usize, count_less: F) -> usize
+where
+    R: RangeBounds<K>,
+    F: Fn(&K) -> (usize, bool),
+{
+    // the number of keys less than or equal to a key
+    let count_to = |k: &K| {
+        let (less, found) = count_less(k);
+        less + usize::from(found)
+    };
+    let start = match range.start_bound() {
+        Bound::Included(k) => count_less(k).0,
+        Bound::Excluded(k) => count_to(k),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(k) => count_to(k),
+        Bound::Excluded(k) => count_less(k).0,
+        Bound::Unbounded => len,
+    };
+    end.saturating_sub(start)
+}
+
+/// Grouping the keys of entries sorted by key under their values
+/// in ascending order of the values, and the keys of
+/// the same value stay in ascending order
+pub(crate) fn group_by_value<'a, K, V, I>(entries: I) -> Vec<(&'a V, Vec<&'a K>)>
+where
+    V: Ord,
+    I: Iterator<Item = (&'a K, &'a V)>,
+{
+    let mut entries: Vec<(&K, &V)> = entries.collect();
+    // the sort is stable so keys of the same value stay sorted
+    entries.sort_by(|a, b| a.1.cmp(b.1));
+    let mut groups: Vec<(&V, Vec<&K>)> = Vec::new();
+    for (k, v) in entries {
+        match groups.last_mut() {
+            Some((last_v, keys)) if (*last_v).eq(v) => keys.push(k),
+            _ => groups.push((v, vec![k])),
+        }
+    }
+    groups
+}